@@ -59,9 +59,9 @@ impl AnimatedSprite {
         )
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, dt: f32) {
         let (_, anim) = self.current_animation_mut();
-        anim.timer.update();
+        anim.timer.update(dt);
         if anim.timer.just_completed() {
             if anim.current_frame + 1 >= anim.frames.len() {
                 if !anim.repeat {
@@ -0,0 +1,56 @@
+use macroquad::prelude::*;
+
+use crate::{
+    floor::Direction,
+    systems::collision::{Collider, ColliderType, Shape},
+};
+
+use super::{
+    entities::Ecs,
+    entity_id::Entity,
+    tags::{RoomEntity, Velocity},
+};
+
+/// Leads to the neighbouring room of the floor once unlocked.
+pub struct Door {
+    pub direction: Direction,
+    /// Index of the room on the floor.
+    pub room: usize,
+    pub locked: bool,
+}
+
+impl Door {
+    /// Wide along the wall the door is in, thin across it.
+    pub fn half_size(&self) -> Vec2 {
+        match self.direction {
+            Direction::North | Direction::South => vec2(8., 4.),
+            Direction::East | Direction::West => vec2(4., 8.),
+        }
+    }
+}
+
+pub fn spawn_door(position: Vec2, direction: Direction, room: usize, ecs: &mut Ecs) -> Entity {
+    let id = ecs.spawn();
+
+    let door = Door {
+        direction,
+        room,
+        locked: true,
+    };
+
+    ecs.components.positions.insert(id, position);
+    ecs.components.velocities.insert(id, Velocity(Vec2::ZERO));
+    ecs.components.colliders.insert(
+        id,
+        Collider::new(
+            Shape::Aabb {
+                half_size: door.half_size(),
+            },
+            ColliderType::Trigger,
+        ),
+    );
+    ecs.components.doors.insert(id, door);
+    ecs.components.room_entity.insert(id, RoomEntity);
+
+    id
+}
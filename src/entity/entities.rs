@@ -1,101 +1,271 @@
+use std::collections::HashMap;
+
 use super::{
     animated_sprite::AnimatedSprite,
-    entity_id::Entity,
+    door::Door,
+    entity_id::{Entity, EntityAllocator},
+    events::EventQueues,
+    hierarchy::{Children, LocalTransform, Muzzle, Parent},
     hopper::Hopper,
-    mirituhg::Mirituhg,
+    mirituhg::{Mirituhg, MirituhgDeath},
     pickup::Pickup,
     player::PlayerData,
+    query::{check_access, Fetch, QueryIter, ReadOnlyFetch},
+    sparse_set::SparseSet,
     spawner::Spawner,
     spitter::Spitter,
     stomper::Stomper,
-    tags::{DamageOnCollision, Damageable, DespawnOnAnimEnd, DespawnOnHit, Health},
+    tags::{
+        AberrationIncrease, Ball, DamageOnCollision, Damageable, DespawnOnAnimEnd, DespawnOnHit,
+        EnemyEntity, FlipToPlayer, Health, IceSlide, LayerOffset, PhysicsBody, PlayerEntity,
+        PreviousPosition, RoomEntity, Velocity,
+    },
+    trigger::TriggerVolume,
+};
+use crate::{
+    physics::{collision::Collision, spatial_hash::SpatialHash},
+    systems::collision::Collider,
+    timer::Timer,
 };
-use crate::{systems::collision::CircleCollider, timer::Timer};
 use macroquad::{material::Material, math::Vec2};
-use std::collections::HashMap;
 
-pub type ComponentColl<T> = HashMap<Entity, T>;
+#[cfg(debug_assertions)]
+use super::inspect::{Field, Inspect};
 
-#[derive(Default)]
-pub struct Components {
-    pub player_data: ComponentColl<PlayerData>,
-    pub timers: ComponentColl<Timer>,
-    pub animated_sprites: ComponentColl<AnimatedSprite>,
-    pub flip_to_player: ComponentColl<()>,
-    pub colliders: ComponentColl<CircleCollider>,
-    pub positions: ComponentColl<Vec2>,
-    pub velocities: ComponentColl<Vec2>,
-    pub spawners: ComponentColl<Spawner>,
-    pub hoppers: ComponentColl<Hopper>,
-    pub spitters: ComponentColl<Spitter>,
-    pub stompers: ComponentColl<Stomper>,
-    pub damage_on_collision: ComponentColl<DamageOnCollision>,
-    pub health: ComponentColl<Health>,
-    pub materials: ComponentColl<Material>,
-    pub damageables: ComponentColl<Damageable>,
-    pub despawn_on_anim_end: ComponentColl<DespawnOnAnimEnd>,
-    pub despawn_on_hit: ComponentColl<DespawnOnHit>,
-    pub player_entity: ComponentColl<()>,
-    pub enemies: ComponentColl<()>,
-    pub room_entity: ComponentColl<()>,
-    pub layer_offset: ComponentColl<i8>,
-    pub pickups: ComponentColl<Pickup>,
-    pub balls: ComponentColl<usize>,
-    pub aberration_increase: ComponentColl<f32>,
-    pub mirituhg: ComponentColl<Mirituhg>,
-    pub mirituhg_death: ComponentColl<()>,
+pub type ComponentColl<T> = SparseSet<T>;
+
+/// A type stored in exactly one field of [`Components`], which lets queries
+/// find its storage from the type alone.
+pub trait Component: Sized + 'static {
+    /// # Safety
+    ///
+    /// `components` must point to a live `Components`.
+    unsafe fn storage_ptr(components: *mut Components) -> *mut ComponentColl<Self>;
+}
+
+/// Declares the component storages. Every type may only appear once, since the
+/// type is what queries use to pick the storage.
+macro_rules! components {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        #[derive(Default)]
+        pub struct Components {
+            $(pub $field: ComponentColl<$ty>,)*
+        }
+
+        impl Components {
+            pub fn remove_all(&mut self, entity: &Entity) {
+                $(self.$field.remove(entity);)*
+            }
+
+            /// Fields of every component the entity has, by storage name.
+            #[cfg(debug_assertions)]
+            pub fn inspect(&mut self, entity: &Entity) -> Vec<(&'static str, Vec<Field<'_>>)> {
+                let mut components = vec![];
+                $(
+                    if let Some(component) = self.$field.get_mut(entity) {
+                        components.push((stringify!($field), component.fields()));
+                    }
+                )*
+                components
+            }
+        }
+
+        $(
+            impl Component for $ty {
+                unsafe fn storage_ptr(components: *mut Components) -> *mut ComponentColl<Self> {
+                    std::ptr::addr_of_mut!((*components).$field)
+                }
+            }
+        )*
+    };
+}
+
+components! {
+    player_data: PlayerData,
+    timers: Timer,
+    animated_sprites: AnimatedSprite,
+    flip_to_player: FlipToPlayer,
+    colliders: Collider,
+    positions: Vec2,
+    previous_positions: PreviousPosition,
+    velocities: Velocity,
+    physics_bodies: PhysicsBody,
+    ice_slides: IceSlide,
+    spawners: Spawner,
+    hoppers: Hopper,
+    spitters: Spitter,
+    stompers: Stomper,
+    damage_on_collision: DamageOnCollision,
+    health: Health,
+    materials: Material,
+    damageables: Damageable,
+    despawn_on_anim_end: DespawnOnAnimEnd,
+    despawn_on_hit: DespawnOnHit,
+    player_entity: PlayerEntity,
+    enemies: EnemyEntity,
+    room_entity: RoomEntity,
+    layer_offset: LayerOffset,
+    pickups: Pickup,
+    balls: Ball,
+    aberration_increase: AberrationIncrease,
+    mirituhg: Mirituhg,
+    mirituhg_death: MirituhgDeath,
+    parents: Parent,
+    children: Children,
+    local_transforms: LocalTransform,
+    muzzles: Muzzle,
+    trigger_volumes: TriggerVolume,
+    doors: Door,
 }
 
 #[derive(Default)]
 pub struct Ecs {
-    pub entities: Vec<Entity>,
+    pub allocator: EntityAllocator,
+    /// Every live entity, dense so queries without a component can iterate
+    /// it and despawning is a swap-remove.
+    pub entities: SparseSet<()>,
     pub components: Components,
     pub marked_for_despawn: Vec<Entity>,
+    pub events: EventQueues,
+    pub collisions: HashMap<(Entity, Entity), Collision>,
+    /// Broadphase of the last physics step.
+    pub spatial_hash: SpatialHash,
 }
 
 impl Ecs {
-    pub fn check_components<P>(&self, predicate: P) -> Vec<Entity>
-    where
-        P: Fn(&Entity, &Components) -> bool,
-    {
-        self.entities
-            .iter()
-            .filter(|e| predicate(e, &self.components))
-            .map(|e| *e)
-            .collect::<Vec<Entity>>()
+    pub fn spawn(&mut self) -> Entity {
+        let id = self.allocator.allocate();
+        self.entities.insert(id, ());
+        id
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.allocator.is_alive(entity)
+    }
+
+    /// Iterates all entities that have every component in `Q`, e.g.
+    /// `ecs.query::<(Entity, &mut Vec2, &Velocity)>()`.
+    ///
+    /// Iteration is driven by the smallest storage in the query.
+    pub fn query<'a, Q: Fetch<'a>>(&'a mut self) -> QueryIter<'a, Q> {
+        check_access::<Q>();
+        let state = unsafe { Q::prepare(&mut self.components) };
+        let entities = Q::driver(&state).unwrap_or(self.entities.entities());
+        QueryIter::new(state, entities)
+    }
+
+    /// Read-only version of [`Ecs::query`].
+    pub fn query_ref<'a, Q: ReadOnlyFetch<'a>>(&'a self) -> QueryIter<'a, Q> {
+        let components = &self.components as *const Components as *mut Components;
+        let state = unsafe { Q::prepare(components) };
+        let entities = Q::driver(&state).unwrap_or(self.entities.entities());
+        QueryIter::new(state, entities)
     }
 
     pub fn despawn(&mut self, entity: Entity) {
         self.marked_for_despawn.push(entity);
     }
 
-    pub fn remove_all_components(&mut self, entity: &Entity) {
-        // TODO: lol
-        self.components.player_data.remove(entity);
-        self.components.timers.remove(entity);
-        self.components.animated_sprites.remove(entity);
-        self.components.flip_to_player.remove(entity);
-        self.components.colliders.remove(entity);
-        self.components.positions.remove(entity);
-        self.components.velocities.remove(entity);
-        self.components.spawners.remove(entity);
-        self.components.hoppers.remove(entity);
-        self.components.spitters.remove(entity);
-        self.components.stompers.remove(entity);
-        self.components.damage_on_collision.remove(entity);
-        self.components.health.remove(entity);
-        self.components.materials.remove(entity);
-        self.components.damageables.remove(entity);
-        self.components.despawn_on_anim_end.remove(entity);
-        self.components.despawn_on_hit.remove(entity);
-        self.components.player_entity.remove(entity);
-        self.components.enemies.remove(entity);
-        self.components.room_entity.remove(entity);
-        self.components.layer_offset.remove(entity);
-        self.components.pickups.remove(entity);
-        self.components.balls.remove(entity);
-        self.components.aberration_increase.remove(entity);
-        self.components.mirituhg.remove(entity);
-        self.components.mirituhg_death.remove(entity);
+    /// Removes the entity and its components right away and frees its index.
+    /// Attached entities are destroyed along with it. Returns false if the
+    /// entity was already gone.
+    pub fn destroy(&mut self, entity: Entity) -> bool {
+        if !self.allocator.free(entity) {
+            return false;
+        }
+
+        self.entities.remove(&entity);
+        if let Some(Parent(parent)) = self.components.parents.get(&entity) {
+            if let Some(siblings) = self.components.children.get_mut(parent) {
+                siblings.0.retain(|e| *e != entity);
+            }
+        }
+        let children = self.components.children.remove(&entity);
+        self.components.remove_all(&entity);
+
+        for child in children.map(|c| c.0).unwrap_or_default() {
+            self.destroy(child);
+        }
+        true
+    }
+
+    /// Destroys the entities marked for despawn. Called by the schedule at its
+    /// sync points.
+    pub fn flush(&mut self) {
+        for entity in std::mem::take(&mut self.marked_for_despawn) {
+            self.destroy(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::math::vec2;
+
+    use crate::entity::hierarchy::attach;
+
+    use super::*;
+
+    #[test]
+    fn stale_handles_see_nothing_after_despawn_and_reuse() {
+        let mut ecs = Ecs::default();
+        let old = ecs.spawn();
+        ecs.components.positions.insert(old, vec2(1., 1.));
+        ecs.despawn(old);
+        ecs.flush();
+
+        let new = ecs.spawn();
+        assert_eq!(new.index, old.index);
+        ecs.components.positions.insert(new, vec2(2., 2.));
+
+        assert!(!ecs.is_alive(old));
+        assert_eq!(ecs.components.positions.get(&old), None);
+        assert_eq!(ecs.components.positions.get(&new), Some(&vec2(2., 2.)));
+        assert_eq!(ecs.entities.entities(), [new]);
+        // Destroying the stale handle leaves the new entity alone
+        assert!(!ecs.destroy(old));
+        assert!(ecs.is_alive(new));
+    }
+
+    #[test]
+    fn flush_destroys_marked_entities_with_their_children() {
+        let mut ecs = Ecs::default();
+        let parent = ecs.spawn();
+        let child = ecs.spawn();
+        let grandchild = ecs.spawn();
+        let other = ecs.spawn();
+        attach(&mut ecs, child, parent, vec2(1., 0.));
+        attach(&mut ecs, grandchild, child, vec2(0., 1.));
+
+        ecs.despawn(parent);
+        // Despawning twice is harmless
+        ecs.despawn(child);
+        assert!(ecs.is_alive(parent));
+        ecs.flush();
+
+        assert!(ecs.marked_for_despawn.is_empty());
+        for e in [parent, child, grandchild] {
+            assert!(!ecs.is_alive(e));
+            assert!(!ecs.components.parents.contains(&e));
+            assert!(!ecs.components.children.contains(&e));
+            assert!(!ecs.components.local_transforms.contains(&e));
+        }
+        assert_eq!(ecs.entities.entities(), [other]);
+    }
+
+    #[test]
+    fn destroying_a_child_detaches_it_from_its_parent() {
+        let mut ecs = Ecs::default();
+        let parent = ecs.spawn();
+        let a = ecs.spawn();
+        let b = ecs.spawn();
+        attach(&mut ecs, a, parent, Vec2::ZERO);
+        attach(&mut ecs, b, parent, Vec2::ZERO);
+
+        ecs.despawn(a);
+        ecs.flush();
+
+        assert!(ecs.is_alive(parent));
+        assert_eq!(ecs.components.children.get(&parent).unwrap().0, [b]);
     }
 }
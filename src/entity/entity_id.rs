@@ -1,8 +1,97 @@
 extern crate proc_macro;
 
+/// Entity handle. `index` addresses the component storages, `generation`
+/// tells apart entities that were given the same index.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-pub struct Entity(pub u64);
+pub struct Entity {
+    pub index: u32,
+    pub generation: u32,
+}
+
+impl Entity {
+    pub fn new(index: u32) -> Self {
+        Self {
+            index,
+            generation: 0,
+        }
+    }
+}
 
 pub trait EntityId {
     fn id(&self) -> Entity;
 }
+
+/// Hands out entity handles, reusing the indices of despawned entities.
+///
+/// Freeing an index bumps its generation, so handles to the old entity stop
+/// being alive even after the index has been given to a new one.
+#[derive(Default)]
+pub struct EntityAllocator {
+    generations: Vec<u32>,
+    free: Vec<u32>,
+}
+
+impl EntityAllocator {
+    pub fn allocate(&mut self) -> Entity {
+        if let Some(index) = self.free.pop() {
+            return Entity {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+
+        self.generations.push(0);
+        Entity::new(self.generations.len() as u32 - 1)
+    }
+
+    pub fn free(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        let generation = &mut self.generations[entity.index as usize];
+        *generation = generation.wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_freed_indices_with_a_new_generation() {
+        let mut allocator = EntityAllocator::default();
+        let a = allocator.allocate();
+        let b = allocator.allocate();
+        assert_ne!(a.index, b.index);
+
+        assert!(allocator.free(a));
+        assert!(!allocator.is_alive(a));
+        let c = allocator.allocate();
+        assert_eq!(c.index, a.index);
+        assert_eq!(c.generation, a.generation + 1);
+        assert!(allocator.is_alive(c));
+        assert!(!allocator.is_alive(a));
+        assert!(allocator.is_alive(b));
+    }
+
+    #[test]
+    fn stale_handles_cant_be_freed() {
+        let mut allocator = EntityAllocator::default();
+        let a = allocator.allocate();
+        assert!(allocator.free(a));
+        assert!(!allocator.free(a));
+
+        // Freeing the stale handle must not free the index's new entity
+        let b = allocator.allocate();
+        assert!(!allocator.free(a));
+        assert!(allocator.is_alive(b));
+        assert!(!allocator.is_alive(Entity::new(7)));
+    }
+}
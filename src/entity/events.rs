@@ -1,9 +1,169 @@
-use super::entity_id::Entity;
+use std::marker::PhantomData;
 
+use super::{entity_id::Entity, mirituhg::MiritughState, pickup::Pickup, upgrades::Upgrade};
+
+#[derive(Clone, Copy)]
 pub struct DamageEvent {
     pub source: Entity,
     pub target: Entity,
     pub damage: f32,
+    pub knockback: f32,
 }
 
+#[derive(Clone, Copy)]
 pub struct DeathEvent(pub Entity);
+
+pub struct PickupCollected {
+    pub collector: Entity,
+    pub pickup: Pickup,
+}
+
+/// Sent by the physics step for a pair of entities that started touching.
+/// The order of the pair is the same as in `Ecs::collisions`.
+#[derive(Clone, Copy)]
+pub struct CollisionStarted(pub Entity, pub Entity);
+
+/// Sent every physics step a pair keeps touching after it started.
+#[derive(Clone, Copy)]
+pub struct CollisionOngoing(pub Entity, pub Entity);
+
+/// Sent once a pair stopped touching, also when one of them was despawned.
+#[derive(Clone, Copy)]
+pub struct CollisionEnded(pub Entity, pub Entity);
+
+pub struct RoomCleared;
+
+pub struct UpgradeChosen(pub Upgrade);
+
+pub struct BossPhaseChanged(pub MiritughState);
+
+/// Double buffered event queue.
+///
+/// Events sent during a frame stay readable until the end of the next one, so
+/// a reader sees every event once no matter if it runs before or after the
+/// sender.
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    previous_start: usize,
+    current_start: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: vec![],
+            current: vec![],
+            previous_start: 0,
+            current_start: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// Drops the events of the previous frame and starts a new one.
+    pub fn update(&mut self) {
+        self.previous_start = self.current_start;
+        self.current_start += self.current.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    fn end(&self) -> usize {
+        self.current_start + self.current.len()
+    }
+}
+
+impl<T> Extend<T> for Events<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, events: I) {
+        self.current.extend(events);
+    }
+}
+
+/// Remembers how far a system has read into an [`Events`] queue.
+pub struct EventReader<T> {
+    next: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> EventReader<T> {
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let start = self.next.max(events.previous_start);
+        self.next = events.end();
+
+        let previous = events.previous.iter().skip(start - events.previous_start);
+        let current = events
+            .current
+            .iter()
+            .skip(start.saturating_sub(events.current_start));
+        previous.chain(current)
+    }
+}
+
+/// Declares the event queues. Like components, each event type may only appear
+/// once so it can be sent and read by type.
+macro_rules! events {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        #[derive(Default)]
+        pub struct EventQueues {
+            $(pub $field: Events<$ty>,)*
+        }
+
+        impl EventQueues {
+            pub fn update(&mut self) {
+                $(self.$field.update();)*
+            }
+        }
+
+        $(
+            impl Event for $ty {
+                fn queue(queues: &EventQueues) -> &Events<Self> {
+                    &queues.$field
+                }
+
+                fn queue_mut(queues: &mut EventQueues) -> &mut Events<Self> {
+                    &mut queues.$field
+                }
+            }
+        )*
+    };
+}
+
+pub trait Event: Sized + 'static {
+    fn queue(queues: &EventQueues) -> &Events<Self>;
+    fn queue_mut(queues: &mut EventQueues) -> &mut Events<Self>;
+}
+
+events! {
+    damage: DamageEvent,
+    death: DeathEvent,
+    pickup_collected: PickupCollected,
+    room_cleared: RoomCleared,
+    upgrade_chosen: UpgradeChosen,
+    boss_phase_changed: BossPhaseChanged,
+    collision_started: CollisionStarted,
+    collision_ongoing: CollisionOngoing,
+    collision_ended: CollisionEnded,
+}
+
+impl EventQueues {
+    pub fn send<T: Event>(&mut self, event: T) {
+        T::queue_mut(self).send(event);
+    }
+
+    pub fn get<T: Event>(&self) -> &Events<T> {
+        T::queue(self)
+    }
+}
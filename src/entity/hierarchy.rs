@@ -0,0 +1,39 @@
+use macroquad::math::Vec2;
+
+use super::{entities::Ecs, entity_id::Entity};
+
+/// Attaches an entity to another one. Its position is derived from the
+/// parent every frame and it is destroyed together with the parent.
+pub struct Parent(pub Entity);
+
+/// Entities attached to this one, kept in sync by [`attach`] and
+/// [`Ecs::destroy`] so a parent finds its children without a scan.
+pub struct Children(pub Vec<Entity>);
+
+/// The attached entity an entity shoots from, e.g. a spitter's mouth.
+pub struct Muzzle(pub Entity);
+
+/// Offset of an attached entity from its parent.
+pub struct LocalTransform {
+    pub translation: Vec2,
+}
+
+/// Makes `child` follow `parent` at the given offset.
+pub fn attach(ecs: &mut Ecs, child: Entity, parent: Entity, translation: Vec2) {
+    if let Some(Parent(old_parent)) = ecs.components.parents.insert(child, Parent(parent)) {
+        if let Some(children) = ecs.components.children.get_mut(&old_parent) {
+            children.0.retain(|e| *e != child);
+        }
+    }
+    match ecs.components.children.get_mut(&parent) {
+        Some(children) => children.0.push(child),
+        None => {
+            ecs.components
+                .children
+                .insert(parent, Children(vec![child]));
+        }
+    }
+    ecs.components
+        .local_transforms
+        .insert(child, LocalTransform { translation });
+}
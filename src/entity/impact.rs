@@ -6,11 +6,11 @@ use super::{
     animated_sprite::{AnimatedSprite, Animation},
     entities::Ecs,
     entity_id::Entity,
-    tags::DespawnOnAnimEnd,
+    tags::{DespawnOnAnimEnd, LayerOffset, RoomEntity},
 };
 
 pub fn spawn_dust(data: &mut GameData, ecs: &mut Ecs, position: Vec2) -> Entity {
-    let id = data.new_entity();
+    let id = ecs.spawn();
 
     let indexed_sprite = IndexedSprite::new(data, "dust", 16, vec2(8., 8.));
     let sprite = AnimatedSprite::new(
@@ -25,14 +25,13 @@ pub fn spawn_dust(data: &mut GameData, ecs: &mut Ecs, position: Vec2) -> Entity
     ecs.components
         .despawn_on_anim_end
         .insert(id, DespawnOnAnimEnd);
-    ecs.components.room_entity.insert(id, ());
+    ecs.components.room_entity.insert(id, RoomEntity);
 
-    ecs.entities.push(id);
     id
 }
 
 pub fn spawn_blood(data: &mut GameData, ecs: &mut Ecs, position: Vec2, index: usize) -> Entity {
-    let id = data.new_entity();
+    let id = ecs.spawn();
 
     let indexed_sprite = IndexedSprite::new(data, "blood", 16, vec2(8., 8.));
     let sprite = AnimatedSprite::new(
@@ -41,10 +40,9 @@ pub fn spawn_blood(data: &mut GameData, ecs: &mut Ecs, position: Vec2, index: us
     );
     ecs.components.animated_sprites.insert(id, sprite);
     ecs.components.positions.insert(id, position);
-    ecs.components.room_entity.insert(id, ());
-    ecs.components.layer_offset.insert(id, -1);
+    ecs.components.room_entity.insert(id, RoomEntity);
+    ecs.components.layer_offset.insert(id, LayerOffset(-1));
 
-    ecs.entities.push(id);
     id
 }
 
@@ -0,0 +1,372 @@
+use macroquad::{material::Material, math::Vec2};
+
+use crate::{
+    systems::collision::{Collider, Shape},
+    timer::Timer,
+};
+
+use super::{
+    animated_sprite::AnimatedSprite,
+    door::Door,
+    hierarchy::{Children, LocalTransform, Muzzle, Parent},
+    hopper::Hopper,
+    mirituhg::{Mirituhg, MirituhgDeath},
+    pickup::Pickup,
+    player::PlayerData,
+    spawner::Spawner,
+    spitter::Spitter,
+    stomper::Stomper,
+    tags::{
+        AberrationIncrease, Ball, DamageOnCollision, Damageable, DespawnOnAnimEnd, DespawnOnHit,
+        EnemyEntity, FlipToPlayer, Health, IceSlide, LayerOffset, PhysicsBody, PlayerEntity,
+        PreviousPosition, RoomEntity, Velocity,
+    },
+    trigger::TriggerVolume,
+};
+
+pub enum FieldValue<'a> {
+    Float(&'a mut f32),
+    Bool(&'a mut bool),
+    Text(String),
+}
+
+pub struct Field<'a> {
+    pub name: String,
+    pub value: FieldValue<'a>,
+}
+
+impl<'a> Field<'a> {
+    fn float(name: &str, value: &'a mut f32) -> Self {
+        Self {
+            name: name.to_string(),
+            value: FieldValue::Float(value),
+        }
+    }
+
+    fn bool(name: &str, value: &'a mut bool) -> Self {
+        Self {
+            name: name.to_string(),
+            value: FieldValue::Bool(value),
+        }
+    }
+
+    fn text(name: &str, value: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            value: FieldValue::Text(value.to_string()),
+        }
+    }
+}
+
+/// Exposes the fields of a component to the inspector. Numbers and flags are
+/// handed out mutably so they can be edited while the game runs, everything
+/// else is shown as text. Tag components keep the empty default.
+pub trait Inspect {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![]
+    }
+}
+
+fn timer_fields<'a>(name: &str, timer: &'a mut Timer) -> Vec<Field<'a>> {
+    let state = format!(
+        "{:.2}{}",
+        timer.progress() * timer.time,
+        if timer.completed() { " done" } else { "" }
+    );
+    vec![
+        Field::text(name, state),
+        Field::float(&format!("{}.time", name), &mut timer.time),
+        Field::bool(&format!("{}.repeating", name), &mut timer.repeating),
+    ]
+}
+
+fn vec2_fields<'a>(name: &str, vec: &'a mut Vec2) -> Vec<Field<'a>> {
+    vec![
+        Field::float(&format!("{}.x", name), &mut vec.x),
+        Field::float(&format!("{}.y", name), &mut vec.y),
+    ]
+}
+
+impl Inspect for PlayerData {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = vec![
+            Field::float("base_move_speed", &mut self.base_move_speed),
+            Field::text("base_max_hp", self.base_max_hp),
+            Field::float("aberration", &mut self.aberration),
+            Field::text("upgrades", self.upgrades.len()),
+        ];
+        fields.extend(timer_fields(
+            "aberration_increase_timer",
+            &mut self.aberration_increase_timer,
+        ));
+        fields
+    }
+}
+
+impl Inspect for Timer {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        timer_fields("timer", self)
+    }
+}
+
+impl Inspect for AnimatedSprite {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let (name, anim) = self.current_animation();
+        let animation = format!("{} {}/{}", name, anim.current_frame + 1, anim.frames.len());
+        vec![
+            Field::text("current_animation", animation),
+            Field::bool("visible", &mut self.visible),
+        ]
+    }
+}
+
+impl Inspect for Collider {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = match &mut self.shape {
+            Shape::Circle { radius } => vec![Field::float("radius", radius)],
+            Shape::Aabb { half_size } => vec2_fields("half_size", half_size),
+            Shape::Capsule { radius, .. } => vec![
+                Field::text("shape", "capsule"),
+                Field::float("radius", radius),
+            ],
+            Shape::Polygon { points } => vec![Field::text("polygon", points.len())],
+        };
+        fields.extend([
+            Field::text("coll_type", format!("{:?}", self.coll_type)),
+            Field::text("layer", format!("{:#010b}", self.layer)),
+            Field::text("mask", format!("{:#010b}", self.mask)),
+            Field::text("response", format!("{:?}", self.response)),
+        ]);
+        fields
+    }
+}
+
+impl Inspect for Vec2 {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec2_fields("pos", self)
+    }
+}
+
+impl Inspect for Velocity {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec2_fields("vel", &mut self.0)
+    }
+}
+
+impl Inspect for PhysicsBody {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = vec![
+            Field::float("mass", &mut self.mass),
+            Field::float("friction", &mut self.friction),
+        ];
+        fields.extend(vec2_fields("knockback", &mut self.knockback));
+        fields
+    }
+}
+
+impl Inspect for IceSlide {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec2_fields("slide", &mut self.0)
+    }
+}
+
+impl Inspect for PreviousPosition {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::text(
+            "pos",
+            format!("{:.2}, {:.2}", self.0.x, self.0.y),
+        )]
+    }
+}
+
+impl Inspect for Spawner {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = vec![
+            Field::bool("active", &mut self.active),
+            Field::text("last_spawn_time", format!("{:.2}", self.last_spawn_time)),
+            Field::text("enemies", format!("{:?}", self.enemies)),
+            Field::text("wave", self.wave),
+            Field::text("count", format!("{:?}", self.count)),
+        ];
+        fields.extend(timer_fields("delay_timer", &mut self.delay_timer));
+        fields
+    }
+}
+
+impl Inspect for Hopper {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = vec![
+            Field::bool("jumping", &mut self.jumping),
+            Field::float("move_speed", &mut self.move_speed),
+            Field::float("jump_move_speed", &mut self.jump_move_speed),
+        ];
+        fields.extend(timer_fields("jump_timer", &mut self.jump_timer));
+        fields
+    }
+}
+
+impl Inspect for Spitter {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = timer_fields("attack_timer", &mut self.attack_timer);
+        fields.extend(timer_fields("spit_timer", &mut self.spit_timer));
+        fields
+    }
+}
+
+impl Inspect for Stomper {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = vec![
+            Field::bool("jumping", &mut self.jumping),
+            Field::float("move_speed", &mut self.move_speed),
+            Field::float("jump_move_speed", &mut self.jump_move_speed),
+        ];
+        fields.extend(timer_fields("damage_timer", &mut self.damage_timer));
+        fields.extend(timer_fields("jump_timer", &mut self.jump_timer));
+        fields
+    }
+}
+
+impl Inspect for DamageOnCollision {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![
+            Field::text("source", format!("{:?}", self.source)),
+            Field::float("damage", &mut self.damage),
+            Field::float("knockback", &mut self.knockback),
+        ]
+    }
+}
+
+impl Inspect for Health {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::float("hp", &mut self.hp)]
+    }
+}
+
+impl Inspect for Damageable {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = vec![];
+        if let Some(timer) = &mut self.invulnerable_timer {
+            fields.extend(timer_fields("invulnerable_timer", timer));
+        }
+        if let Some(timer) = &mut self.hit_fx_timer {
+            fields.extend(timer_fields("hit_fx_timer", timer));
+        }
+        fields
+    }
+}
+
+impl Inspect for DespawnOnHit {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::text("target", format!("{:?}", self.0))]
+    }
+}
+
+impl Inspect for LayerOffset {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::text("offset", self.0)]
+    }
+}
+
+impl Inspect for Pickup {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        match self {
+            Pickup::Health(hp) => vec![Field::float("health", hp)],
+            _ => vec![Field::text("kind", format!("{:?}", self))],
+        }
+    }
+}
+
+impl Inspect for Ball {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::text("index", self.0)]
+    }
+}
+
+impl Inspect for AberrationIncrease {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::float("increase", &mut self.0)]
+    }
+}
+
+impl Inspect for Mirituhg {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        let mut fields = vec![
+            Field::text("state", format!("{:?}", self.state)),
+            Field::float("move_speed", &mut self.move_speed),
+            Field::float("shoot_move_speed", &mut self.shoot_move_speed),
+            Field::float("max_hp", &mut self.max_hp),
+            Field::bool("previously_spawned", &mut self.previously_spawned),
+            Field::bool("spawning", &mut self.spawning),
+            Field::float("shoot_rotation", &mut self.shoot_rotation),
+        ];
+        fields.extend(vec2_fields("target_pos", &mut self.target_pos));
+        fields.extend(timer_fields(
+            "next_attack_timer",
+            &mut self.next_attack_timer,
+        ));
+        fields.extend(timer_fields("shoot_timer", &mut self.shoot_timer));
+        fields.extend(timer_fields("spawn_1_timer", &mut self.spawn_1_timer));
+        fields.extend(timer_fields("spawn_2_timer", &mut self.spawn_2_timer));
+        fields.extend(timer_fields("spawn_3_timer", &mut self.spawn_3_timer));
+        fields.extend(timer_fields("next_move_timer", &mut self.next_move_timer));
+        fields
+    }
+}
+
+impl Inspect for Parent {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::text(
+            "parent",
+            format!("{} gen {}", self.0.index, self.0.generation),
+        )]
+    }
+}
+
+impl Inspect for Children {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::text("children", format!("{}", self.0.len()))]
+    }
+}
+
+impl Inspect for Muzzle {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![Field::text(
+            "muzzle",
+            format!("{} gen {}", self.0.index, self.0.generation),
+        )]
+    }
+}
+
+impl Inspect for LocalTransform {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec2_fields("translation", &mut self.translation)
+    }
+}
+
+impl Inspect for TriggerVolume {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![
+            Field::text("on_enter", format!("{:?}", self.on_enter)),
+            Field::text("on_exit", format!("{:?}", self.on_exit)),
+            Field::bool("once", &mut self.once),
+        ]
+    }
+}
+
+impl Inspect for Door {
+    fn fields(&mut self) -> Vec<Field<'_>> {
+        vec![
+            Field::text("direction", format!("{:?}", self.direction)),
+            Field::text("room", self.room),
+            Field::bool("locked", &mut self.locked),
+        ]
+    }
+}
+
+impl Inspect for Material {}
+impl Inspect for FlipToPlayer {}
+impl Inspect for DespawnOnAnimEnd {}
+impl Inspect for PlayerEntity {}
+impl Inspect for EnemyEntity {}
+impl Inspect for RoomEntity {}
+impl Inspect for MirituhgDeath {}
@@ -1,16 +1,24 @@
 pub mod animated_sprite;
+pub mod door;
 pub mod entities;
 pub mod entity_id;
 pub mod events;
+pub mod hierarchy;
 pub mod hopper;
 pub mod impact;
+#[cfg(debug_assertions)]
+pub mod inspect;
 pub mod mirituhg;
 pub mod pickup;
 pub mod player;
+pub mod prefab;
 pub mod projectile;
+pub mod query;
 pub mod skull;
+pub mod sparse_set;
 pub mod spawner;
 pub mod spitter;
 pub mod stomper;
 pub mod tags;
+pub mod trigger;
 pub mod upgrades;
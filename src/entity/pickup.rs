@@ -1,21 +1,11 @@
-use std::collections::HashMap;
-
 use macroquad::prelude::*;
+use nanoserde::DeJson;
 
-use crate::{
-    game_data::GameData,
-    sprite::{flash_material::create_sprite_color_material, indexed_sprite::IndexedSprite},
-    systems::collision::{CircleCollider, ColliderType},
-};
+use crate::game_data::GameData;
 
-use super::{
-    animated_sprite::{AnimatedSprite, Animation},
-    entities::Ecs,
-    entity_id::Entity,
-    tags::{DespawnOnHit, EntityType},
-};
+use super::{entities::Ecs, entity_id::Entity, prefab::spawn_prefab};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, DeJson)]
 pub enum Pickup {
     Health(f32),
     AnomalyBig,
@@ -23,40 +13,13 @@ pub enum Pickup {
 }
 
 pub fn spawn_pickup(data: &mut GameData, position: Vec2, ecs: &mut Ecs, pickup: Pickup) -> Entity {
-    let id = data.new_entity();
-
-    let texture = match pickup {
+    let prefab = match pickup {
         Pickup::Health(_) => "health",
         Pickup::AnomalyBig => "anomaly_big",
         Pickup::AnomalySmall => "anomaly_small",
     };
-    let indexed_sprite = IndexedSprite::new(data, texture, 16, vec2(8., 9.));
-    let sprite = AnimatedSprite::new(
-        indexed_sprite,
-        HashMap::from([("noop".to_string(), Animation::new(vec![0], 0., false))]),
-    );
-    ecs.components.animated_sprites.insert(id, sprite);
-
-    let collider = CircleCollider {
-        radius: 3.,
-        coll_type: ColliderType::Pickup,
-    };
-    ecs.components.colliders.insert(id, collider);
-
-    ecs.components.positions.insert(id, position);
-    ecs.components.velocities.insert(id, Vec2::ZERO);
-
+    let id = spawn_prefab(data, ecs, prefab, position);
     ecs.components.pickups.insert(id, pickup);
 
-    // ecs.components
-    //     .materials
-    //     .insert(id, create_sprite_color_material());
-
-    ecs.components.room_entity.insert(id, ());
-    ecs.components
-        .despawn_on_hit
-        .insert(id, DespawnOnHit(EntityType::Pickup));
-
-    ecs.entities.push(id);
     id
 }
@@ -1,7 +1,7 @@
 use crate::{
     game_data::GameData,
     sprite::{flash_material::create_sprite_color_material, indexed_sprite::IndexedSprite},
-    systems::collision::{CircleCollider, ColliderType},
+    systems::collision::{Collider, ColliderType},
     timer::Timer,
 };
 use macroquad::prelude::*;
@@ -11,7 +11,7 @@ use super::{
     animated_sprite::{AnimatedSprite, Animation},
     entities::Ecs,
     entity_id::Entity,
-    tags::{Damageable, Health},
+    tags::{Damageable, Health, PlayerEntity, Velocity},
     upgrades::CommonUpgrade,
 };
 
@@ -58,7 +58,7 @@ pub fn spawn_player(data: &mut GameData, ecs: &mut Ecs) -> Entity {
     );
 
     // Shadows
-    let shadow1_id = data.new_entity();
+    let shadow1_id = ecs.spawn();
     let mut shadow1_sprite = sprite.clone();
     shadow1_sprite.color = Color::from_rgba(255, 255, 255, 120);
     shadow1_sprite.visible = false;
@@ -68,13 +68,14 @@ pub fn spawn_player(data: &mut GameData, ecs: &mut Ecs) -> Entity {
     ecs.components
         .positions
         .insert(shadow1_id, vec2(180., 120.));
-    ecs.components.player_entity.insert(shadow1_id, ());
+    ecs.components
+        .player_entity
+        .insert(shadow1_id, PlayerEntity);
     // ecs.components
     //     .materials
     //     .insert(shadow1_id, create_sprite_color_material());
-    ecs.entities.push(shadow1_id);
 
-    let shadow2_id = data.new_entity();
+    let shadow2_id = ecs.spawn();
     let mut shadow2_sprite = sprite.clone();
     shadow2_sprite.color = Color::from_rgba(255, 255, 255, 220);
     shadow2_sprite.visible = false;
@@ -84,24 +85,22 @@ pub fn spawn_player(data: &mut GameData, ecs: &mut Ecs) -> Entity {
     ecs.components
         .positions
         .insert(shadow2_id, vec2(180., 120.));
-    ecs.components.player_entity.insert(shadow2_id, ());
+    ecs.components
+        .player_entity
+        .insert(shadow2_id, PlayerEntity);
     // ecs.components
     //     .materials
     //     .insert(shadow2_id, create_sprite_color_material());
-    ecs.entities.push(shadow2_id);
 
     // Player
-    let id = data.new_entity();
+    let id = ecs.spawn();
     ecs.components.animated_sprites.insert(id, sprite.clone());
 
-    let collider = CircleCollider {
-        radius: 3.,
-        coll_type: ColliderType::Player,
-    };
+    let collider = Collider::circle(3., ColliderType::Player);
     ecs.components.colliders.insert(id, collider);
 
     ecs.components.positions.insert(id, vec2(180., 120.));
-    ecs.components.velocities.insert(id, Vec2::ZERO);
+    ecs.components.velocities.insert(id, Velocity(Vec2::ZERO));
 
     let player_data = PlayerData {
         base_move_speed: 72.,
@@ -134,9 +133,7 @@ pub fn spawn_player(data: &mut GameData, ecs: &mut Ecs) -> Entity {
 
     println!("PLAYER {:?}", id);
 
-    ecs.components.player_entity.insert(id, ());
-
-    ecs.entities.push(id);
+    ecs.components.player_entity.insert(id, PlayerEntity);
 
     id
 }
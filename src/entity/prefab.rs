@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+use nanoserde::DeJson;
+
+use crate::{
+    game_data::GameData,
+    sprite::{flash_material::create_sprite_color_material, indexed_sprite::IndexedSprite},
+    systems::collision::{Collider, ColliderType, Shape},
+    timer::Timer,
+};
+
+use super::{
+    animated_sprite::{AnimatedSprite, Animation},
+    entities::Ecs,
+    entity_id::Entity,
+    hierarchy::{attach, Muzzle},
+    hopper::Hopper,
+    mirituhg::{MiritughState, Mirituhg, MirituhgDeath},
+    pickup::Pickup,
+    spitter::Spitter,
+    stomper::Stomper,
+    tags::{
+        AberrationIncrease, DamageOnCollision, Damageable, DespawnOnHit, EnemyEntity, EntityType,
+        FlipToPlayer, Health, PhysicsBody, PlayerEntity, RoomEntity, Velocity,
+    },
+};
+
+/// Component set of an archetype, loaded from `assets/prefabs/<name>.json`.
+/// Every component is optional, only the ones present get inserted.
+#[derive(DeJson)]
+pub struct Prefab {
+    pub sprite: Option<SpriteDef>,
+    #[nserde(default)]
+    pub flip_to_player: bool,
+    pub collider: Option<ColliderDef>,
+    pub velocity: Option<[f32; 2]>,
+    pub physics_body: Option<PhysicsBodyDef>,
+    pub health: Option<f32>,
+    pub damageable: Option<DamageableDef>,
+    pub damage_on_collision: Option<DamageOnCollisionDef>,
+    pub despawn_on_hit: Option<EntityType>,
+    #[nserde(default)]
+    pub flash_material: bool,
+    #[nserde(default)]
+    pub room_entity: bool,
+    #[nserde(default)]
+    pub enemy: bool,
+    /// How much of a room's difficulty budget the enemy takes up. Rooms and
+    /// spawn points that don't name their enemies pick from the prefabs that
+    /// have one.
+    pub spawn_cost: Option<f32>,
+    #[nserde(default)]
+    pub player_entity: bool,
+    pub aberration_increase: Option<f32>,
+    pub pickup: Option<Pickup>,
+    pub hopper: Option<HopperDef>,
+    pub spitter: Option<SpitterDef>,
+    pub stomper: Option<StomperDef>,
+    /// Offset of the point it shoots from, spawned as an attached entity.
+    pub muzzle: Option<[f32; 2]>,
+    pub mirituhg: Option<MirituhgDef>,
+    #[nserde(default)]
+    pub mirituhg_death: bool,
+}
+
+#[derive(DeJson)]
+pub struct SpriteDef {
+    /// Path of the texture in `assets`.
+    pub texture: String,
+    pub frame_width: u32,
+    pub offset: [f32; 2],
+    pub animations: HashMap<String, AnimationDef>,
+    pub animation: String,
+}
+
+#[derive(DeJson)]
+pub struct AnimationDef {
+    pub frames: Vec<usize>,
+    pub frame_time: f32,
+    #[nserde(default)]
+    pub repeat: bool,
+}
+
+/// A circle of `radius` unless one of the other shapes is given. `segment`
+/// makes a capsule grown by `radius`.
+#[derive(DeJson)]
+pub struct ColliderDef {
+    #[nserde(default)]
+    pub radius: f32,
+    pub half_size: Option<[f32; 2]>,
+    pub segment: Option<[[f32; 2]; 2]>,
+    pub polygon: Option<Vec<[f32; 2]>>,
+    #[nserde(rename = "type")]
+    pub coll_type: ColliderType,
+}
+
+impl ColliderDef {
+    fn shape(&self) -> Shape {
+        if let Some(half_size) = self.half_size {
+            Shape::Aabb {
+                half_size: Vec2::from(half_size),
+            }
+        } else if let Some([a, b]) = self.segment {
+            Shape::Capsule {
+                a: Vec2::from(a),
+                b: Vec2::from(b),
+                radius: self.radius,
+            }
+        } else if let Some(points) = &self.polygon {
+            Shape::Polygon {
+                points: points.iter().map(|p| Vec2::from(*p)).collect(),
+            }
+        } else {
+            Shape::Circle {
+                radius: self.radius,
+            }
+        }
+    }
+}
+
+#[derive(DeJson)]
+pub struct DamageableDef {
+    pub invulnerable_time: Option<f32>,
+    pub hit_fx_time: Option<f32>,
+}
+
+#[derive(DeJson)]
+pub struct PhysicsBodyDef {
+    pub mass: f32,
+    pub friction: f32,
+}
+
+#[derive(DeJson)]
+pub struct DamageOnCollisionDef {
+    pub source: EntityType,
+    pub damage: f32,
+    #[nserde(default)]
+    pub knockback: f32,
+}
+
+#[derive(DeJson)]
+pub struct HopperDef {
+    pub jump_time: f32,
+    pub move_speed: f32,
+    pub jump_move_speed: f32,
+}
+
+#[derive(DeJson)]
+pub struct SpitterDef {
+    pub attack_time: f32,
+    pub spit_time: f32,
+}
+
+#[derive(DeJson)]
+pub struct StomperDef {
+    pub damage_time: f32,
+    pub jump_time: f32,
+    pub move_speed: f32,
+    pub jump_move_speed: f32,
+}
+
+#[derive(DeJson)]
+pub struct MirituhgDef {
+    pub move_speed: f32,
+    pub shoot_move_speed: f32,
+    pub next_attack_time: f32,
+    pub shoot_time: f32,
+    pub spawn_times: [f32; 3],
+    pub next_move_time: f32,
+}
+
+/// Loads every prefab listed in `assets/prefabs/prefabs.json`, along with the
+/// textures of their sprites. They are read at startup so they can be changed
+/// or added without recompiling.
+pub async fn load_prefabs(textures: &mut HashMap<String, Texture2D>) -> HashMap<String, Prefab> {
+    let index = load_string("prefabs/prefabs.json").await.unwrap();
+    let names = Vec::<String>::deserialize_json(&index)
+        .unwrap_or_else(|e| panic!("invalid prefab list: {}", e));
+
+    let mut prefabs = HashMap::new();
+    for name in names {
+        let json = load_string(&format!("prefabs/{}.json", name))
+            .await
+            .unwrap();
+        let prefab = Prefab::deserialize_json(&json)
+            .unwrap_or_else(|e| panic!("invalid prefab `{}`: {}", name, e));
+        if let Some(sprite) = &prefab.sprite {
+            if !textures.contains_key(&sprite.texture) {
+                let texture = load_texture(&sprite.texture)
+                    .await
+                    .unwrap_or_else(|e| panic!("invalid texture `{}`: {}", sprite.texture, e));
+                texture.set_filter(FilterMode::Nearest);
+                textures.insert(sprite.texture.clone(), texture);
+            }
+        }
+        prefabs.insert(name, prefab);
+    }
+    prefabs
+}
+
+/// Names and spawn costs of the enemies rooms spawn on their own, sorted by
+/// name.
+pub fn regular_enemies(prefabs: &HashMap<String, Prefab>) -> Vec<(&str, f32)> {
+    let mut enemies = prefabs
+        .iter()
+        .filter_map(|(name, prefab)| Some((name.as_str(), prefab.spawn_cost?)))
+        .collect::<Vec<_>>();
+    enemies.sort_by_key(|(name, _)| *name);
+    enemies
+}
+
+pub fn spawn_prefab(data: &GameData, ecs: &mut Ecs, name: &str, position: Vec2) -> Entity {
+    let prefab = data
+        .prefabs
+        .get(name)
+        .unwrap_or_else(|| panic!("unknown prefab `{}`", name));
+
+    let id = ecs.spawn();
+    ecs.components.positions.insert(id, position);
+
+    if let Some(sprite) = &prefab.sprite {
+        let indexed_sprite = IndexedSprite::new(
+            data,
+            &sprite.texture,
+            sprite.frame_width,
+            Vec2::from(sprite.offset),
+        );
+        let animations = sprite
+            .animations
+            .iter()
+            .map(|(name, anim)| {
+                (
+                    name.clone(),
+                    Animation::new(anim.frames.clone(), anim.frame_time, anim.repeat),
+                )
+            })
+            .collect();
+        let mut animated_sprite = AnimatedSprite::new(indexed_sprite, animations);
+        animated_sprite.set_animation(&sprite.animation);
+        ecs.components.animated_sprites.insert(id, animated_sprite);
+    }
+    if prefab.flip_to_player {
+        ecs.components.flip_to_player.insert(id, FlipToPlayer);
+    }
+
+    if let Some(collider) = &prefab.collider {
+        ecs.components
+            .colliders
+            .insert(id, Collider::new(collider.shape(), collider.coll_type));
+    }
+    if let Some(velocity) = prefab.velocity {
+        ecs.components
+            .velocities
+            .insert(id, Velocity(Vec2::from(velocity)));
+    }
+    if let Some(body) = &prefab.physics_body {
+        ecs.components
+            .physics_bodies
+            .insert(id, PhysicsBody::new(body.mass, body.friction));
+    }
+
+    if let Some(hp) = prefab.health {
+        ecs.components.health.insert(id, Health { hp });
+    }
+    if let Some(damageable) = &prefab.damageable {
+        ecs.components.damageables.insert(
+            id,
+            Damageable {
+                invulnerable_timer: damageable
+                    .invulnerable_time
+                    .map(|time| Timer::new(time, false)),
+                hit_fx_timer: damageable.hit_fx_time.map(|time| Timer::new(time, false)),
+            },
+        );
+    }
+    if let Some(damage) = &prefab.damage_on_collision {
+        ecs.components.damage_on_collision.insert(
+            id,
+            DamageOnCollision {
+                source: damage.source,
+                damage: damage.damage,
+                knockback: damage.knockback,
+            },
+        );
+    }
+    if let Some(target) = prefab.despawn_on_hit {
+        ecs.components
+            .despawn_on_hit
+            .insert(id, DespawnOnHit(target));
+    }
+
+    if prefab.flash_material {
+        ecs.components
+            .materials
+            .insert(id, create_sprite_color_material());
+    }
+    if prefab.room_entity {
+        ecs.components.room_entity.insert(id, RoomEntity);
+    }
+    if prefab.enemy {
+        ecs.components.enemies.insert(id, EnemyEntity);
+    }
+    if prefab.player_entity {
+        ecs.components.player_entity.insert(id, PlayerEntity);
+    }
+    if let Some(increase) = prefab.aberration_increase {
+        ecs.components
+            .aberration_increase
+            .insert(id, AberrationIncrease(increase));
+    }
+    if let Some(pickup) = prefab.pickup {
+        ecs.components.pickups.insert(id, pickup);
+    }
+
+    if let Some(hopper) = &prefab.hopper {
+        ecs.components.hoppers.insert(
+            id,
+            Hopper {
+                jump_timer: Timer::new(hopper.jump_time, false),
+                jumping: false,
+                move_speed: hopper.move_speed,
+                jump_move_speed: hopper.jump_move_speed,
+            },
+        );
+    }
+    if let Some(spitter) = &prefab.spitter {
+        ecs.components.spitters.insert(
+            id,
+            Spitter {
+                attack_timer: Timer::new(spitter.attack_time, false),
+                spit_timer: Timer::new(spitter.spit_time, false),
+            },
+        );
+    }
+    if let Some(stomper) = &prefab.stomper {
+        ecs.components.stompers.insert(
+            id,
+            Stomper {
+                damage_timer: Timer::new(stomper.damage_time, false),
+                jump_timer: Timer::new(stomper.jump_time, false),
+                move_speed: stomper.move_speed,
+                jump_move_speed: stomper.jump_move_speed,
+                jumping: false,
+            },
+        );
+    }
+    if let Some(mirituhg) = &prefab.mirituhg {
+        let [spawn_1_time, spawn_2_time, spawn_3_time] = mirituhg.spawn_times;
+        ecs.components.mirituhg.insert(
+            id,
+            Mirituhg {
+                move_speed: mirituhg.move_speed,
+                shoot_move_speed: mirituhg.shoot_move_speed,
+                max_hp: prefab.health.unwrap_or(1.),
+                state: MiritughState::Idle,
+                previously_spawned: false,
+                spawning: false,
+                next_attack_timer: Timer::new(mirituhg.next_attack_time, false),
+                shoot_timer: Timer::new(mirituhg.shoot_time, false),
+                spawn_1_timer: Timer::new(spawn_1_time, false),
+                spawn_2_timer: Timer::new(spawn_2_time, false),
+                spawn_3_timer: Timer::new(spawn_3_time, false),
+                next_move_timer: Timer::new(mirituhg.next_move_time, true),
+                target_pos: position,
+                shoot_rotation: 0.,
+            },
+        );
+    }
+    if prefab.mirituhg_death {
+        ecs.components.mirituhg_death.insert(id, MirituhgDeath);
+    }
+
+    if let Some(offset) = prefab.muzzle {
+        let muzzle = ecs.spawn();
+        ecs.components
+            .positions
+            .insert(muzzle, position + Vec2::from(offset));
+        attach(ecs, muzzle, id, Vec2::from(offset));
+        ecs.components.muzzles.insert(id, Muzzle(muzzle));
+    }
+
+    id
+}
@@ -1,16 +1,14 @@
 use crate::{
     game_data::GameData,
-    sprite::indexed_sprite::IndexedSprite,
-    systems::collision::{CircleCollider, ColliderType},
+    systems::collision::{Collider, ColliderType},
 };
 use macroquad::prelude::*;
-use std::collections::HashMap;
 
 use super::{
-    animated_sprite::{AnimatedSprite, Animation},
     entities::Ecs,
     entity_id::Entity,
-    tags::{DamageOnCollision, DespawnOnHit, EntityType},
+    prefab::spawn_prefab,
+    tags::{EntityType, Velocity},
 };
 
 pub fn spawn_bullet(
@@ -22,46 +20,20 @@ pub fn spawn_bullet(
     velocity: Vec2,
     collider_type: ColliderType,
 ) -> Entity {
-    let id = data.new_entity();
-
-    let texture = if target == EntityType::Player {
+    let prefab = if target == EntityType::Player {
         "bullet_enemy"
     } else {
         "bullet"
     };
-    let indexed_sprite = IndexedSprite::new(data, texture, 16, vec2(8., 8.));
-    let sprite = AnimatedSprite::new(
-        indexed_sprite,
-        HashMap::from([("idle".to_string(), Animation::new(vec![0], 4., false))]),
-    );
-    ecs.components.animated_sprites.insert(id, sprite);
-    ecs.components.positions.insert(id, position);
-    ecs.components
-        .despawn_on_hit
-        .insert(id, DespawnOnHit(target));
+    let id = spawn_prefab(data, ecs, prefab, position);
 
-    ecs.components.colliders.insert(
-        id,
-        CircleCollider {
-            radius: 2.5,
-            coll_type: collider_type,
-        },
-    );
-    ecs.components.damage_on_collision.insert(
-        id,
-        DamageOnCollision {
-            damage,
-            source: if target == EntityType::Player {
-                EntityType::Enemy
-            } else {
-                EntityType::Player
-            },
-        },
-    );
-    ecs.components.velocities.insert(id, velocity);
-    ecs.components.player_entity.insert(id, ());
-    ecs.components.room_entity.insert(id, ());
+    if let Some(collider) = ecs.components.colliders.get_mut(&id) {
+        *collider = Collider::new(collider.shape.clone(), collider_type);
+    }
+    if let Some(damage_on_collision) = ecs.components.damage_on_collision.get_mut(&id) {
+        damage_on_collision.damage = damage;
+    }
+    ecs.components.velocities.insert(id, Velocity(velocity));
 
-    ecs.entities.push(id);
     id
 }
@@ -0,0 +1,275 @@
+use std::any::TypeId;
+
+use super::{
+    entities::{Component, Components},
+    entity_id::Entity,
+    sparse_set::lookup,
+};
+
+/// One element of a query: `&T`, `&mut T`, `Option<&T>`, `Entity`, or a
+/// tuple of those.
+///
+/// # Safety
+///
+/// `fetch` hands out references derived from raw pointers. Implementors must
+/// report every component they borrow in `access` so that [`check_access`]
+/// can reject queries that alias the same storage mutably.
+pub unsafe trait Fetch<'a> {
+    type Item;
+    type State;
+
+    fn access(access: &mut Vec<(TypeId, bool)>);
+
+    /// # Safety
+    ///
+    /// `components` must be valid for `'a` and, for mutable fetches, not
+    /// borrowed anywhere else for that lifetime.
+    unsafe fn prepare(components: *mut Components) -> Self::State;
+
+    /// Entities the fetch is limited to, if it is backed by a storage.
+    fn driver(state: &Self::State) -> Option<&'a [Entity]>;
+
+    /// # Safety
+    ///
+    /// Must not be called twice with the same entity for one state while a
+    /// previously returned mutable item is still alive.
+    unsafe fn fetch(state: &Self::State, entity: Entity) -> Option<Self::Item>;
+}
+
+/// Fetches that never borrow a storage mutably and can run on `&Ecs`.
+pub unsafe trait ReadOnlyFetch<'a>: Fetch<'a> {}
+
+pub struct QueryIter<'a, Q: Fetch<'a>> {
+    state: Q::State,
+    entities: std::slice::Iter<'a, Entity>,
+}
+
+impl<'a, Q: Fetch<'a>> QueryIter<'a, Q> {
+    pub(super) fn new(state: Q::State, entities: &'a [Entity]) -> Self {
+        Self {
+            state,
+            entities: entities.iter(),
+        }
+    }
+}
+
+impl<'a, Q: Fetch<'a>> Iterator for QueryIter<'a, Q> {
+    type Item = Q::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for entity in self.entities.by_ref() {
+            // Driver entities are unique, so each item is fetched only once
+            if let Some(item) = unsafe { Q::fetch(&self.state, *entity) } {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+pub(super) fn check_access<'a, Q: Fetch<'a>>() {
+    let mut access = vec![];
+    Q::access(&mut access);
+    for (i, (type_id, mutable)) in access.iter().enumerate() {
+        for (other_id, other_mutable) in &access[i + 1..] {
+            if type_id == other_id && (*mutable || *other_mutable) {
+                panic!("query borrows the same component mutably more than once");
+            }
+        }
+    }
+}
+
+unsafe impl<'a> Fetch<'a> for Entity {
+    type Item = Entity;
+    type State = ();
+
+    fn access(_access: &mut Vec<(TypeId, bool)>) {}
+
+    unsafe fn prepare(_components: *mut Components) -> Self::State {}
+
+    fn driver(_state: &Self::State) -> Option<&'a [Entity]> {
+        None
+    }
+
+    unsafe fn fetch(_state: &Self::State, entity: Entity) -> Option<Self::Item> {
+        Some(entity)
+    }
+}
+
+unsafe impl<'a> ReadOnlyFetch<'a> for Entity {}
+
+unsafe impl<'a, T: Component> Fetch<'a> for &'a T {
+    type Item = &'a T;
+    type State = (&'a [Option<usize>], &'a [Entity], &'a [T]);
+
+    fn access(access: &mut Vec<(TypeId, bool)>) {
+        access.push((TypeId::of::<T>(), false));
+    }
+
+    unsafe fn prepare(components: *mut Components) -> Self::State {
+        (*T::storage_ptr(components)).split()
+    }
+
+    fn driver(state: &Self::State) -> Option<&'a [Entity]> {
+        Some(state.1)
+    }
+
+    unsafe fn fetch(state: &Self::State, entity: Entity) -> Option<Self::Item> {
+        let index = lookup(state.0, state.1, entity)?;
+        Some(&state.2[index])
+    }
+}
+
+unsafe impl<'a, T: Component> ReadOnlyFetch<'a> for &'a T {}
+
+unsafe impl<'a, T: Component> Fetch<'a> for &'a mut T {
+    type Item = &'a mut T;
+    type State = (&'a [Option<usize>], &'a [Entity], *mut T);
+
+    fn access(access: &mut Vec<(TypeId, bool)>) {
+        access.push((TypeId::of::<T>(), true));
+    }
+
+    unsafe fn prepare(components: *mut Components) -> Self::State {
+        let (sparse, dense, data) = (*T::storage_ptr(components)).split_mut();
+        (sparse, dense, data.as_mut_ptr())
+    }
+
+    fn driver(state: &Self::State) -> Option<&'a [Entity]> {
+        Some(state.1)
+    }
+
+    unsafe fn fetch(state: &Self::State, entity: Entity) -> Option<Self::Item> {
+        let index = lookup(state.0, state.1, entity)?;
+        Some(&mut *state.2.add(index))
+    }
+}
+
+unsafe impl<'a, T: Component> Fetch<'a> for Option<&'a T> {
+    type Item = Option<&'a T>;
+    type State = (&'a [Option<usize>], &'a [Entity], &'a [T]);
+
+    fn access(access: &mut Vec<(TypeId, bool)>) {
+        access.push((TypeId::of::<T>(), false));
+    }
+
+    unsafe fn prepare(components: *mut Components) -> Self::State {
+        (*T::storage_ptr(components)).split()
+    }
+
+    fn driver(_state: &Self::State) -> Option<&'a [Entity]> {
+        None
+    }
+
+    unsafe fn fetch(state: &Self::State, entity: Entity) -> Option<Self::Item> {
+        Some(lookup(state.0, state.1, entity).map(|index| &state.2[index]))
+    }
+}
+
+unsafe impl<'a, T: Component> ReadOnlyFetch<'a> for Option<&'a T> {}
+
+macro_rules! impl_fetch_tuple {
+    ($($name:ident),+) => {
+        #[allow(non_snake_case)]
+        unsafe impl<'a, $($name: Fetch<'a>),+> Fetch<'a> for ($($name,)+) {
+            type Item = ($($name::Item,)+);
+            type State = ($($name::State,)+);
+
+            fn access(access: &mut Vec<(TypeId, bool)>) {
+                $($name::access(access);)+
+            }
+
+            unsafe fn prepare(components: *mut Components) -> Self::State {
+                ($($name::prepare(components),)+)
+            }
+
+            fn driver(state: &Self::State) -> Option<&'a [Entity]> {
+                let ($($name,)+) = state;
+                let mut driver: Option<&'a [Entity]> = None;
+                $(
+                    if let Some(entities) = $name::driver($name) {
+                        if driver.map_or(true, |d| entities.len() < d.len()) {
+                            driver = Some(entities);
+                        }
+                    }
+                )+
+                driver
+            }
+
+            unsafe fn fetch(state: &Self::State, entity: Entity) -> Option<Self::Item> {
+                let ($($name,)+) = state;
+                Some(($($name::fetch($name, entity)?,)+))
+            }
+        }
+
+        unsafe impl<'a, $($name: ReadOnlyFetch<'a>),+> ReadOnlyFetch<'a> for ($($name,)+) {}
+    };
+}
+
+impl_fetch_tuple!(A);
+impl_fetch_tuple!(A, B);
+impl_fetch_tuple!(A, B, C);
+impl_fetch_tuple!(A, B, C, D);
+impl_fetch_tuple!(A, B, C, D, E);
+impl_fetch_tuple!(A, B, C, D, E, F);
+impl_fetch_tuple!(A, B, C, D, E, F, G);
+impl_fetch_tuple!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use macroquad::math::{vec2, Vec2};
+
+    use crate::entity::{entities::Ecs, tags::Velocity};
+
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "mutably more than once")]
+    fn rejects_two_mutable_borrows_of_a_component() {
+        let mut ecs = Ecs::default();
+        let _ = ecs.query::<(&mut Vec2, &mut Vec2)>();
+    }
+
+    #[test]
+    #[should_panic(expected = "mutably more than once")]
+    fn rejects_a_shared_and_a_mutable_borrow_of_a_component() {
+        let mut ecs = Ecs::default();
+        let _ = ecs.query::<(&Vec2, Entity, Option<&Vec2>, &mut Vec2)>();
+    }
+
+    #[test]
+    fn allows_shared_borrows_of_a_component() {
+        let mut ecs = Ecs::default();
+        let e = ecs.spawn();
+        ecs.components.positions.insert(e, vec2(1., 2.));
+
+        let items = ecs.query::<(&Vec2, &Vec2)>().collect::<Vec<_>>();
+        assert_eq!(items, [(&vec2(1., 2.), &vec2(1., 2.))]);
+    }
+
+    #[test]
+    fn yields_the_entities_with_every_component() {
+        let mut ecs = Ecs::default();
+        let a = ecs.spawn();
+        let b = ecs.spawn();
+        let c = ecs.spawn();
+        for e in [a, b, c] {
+            ecs.components.positions.insert(e, Vec2::ZERO);
+        }
+        ecs.components.velocities.insert(a, Velocity(vec2(1., 0.)));
+        ecs.components.velocities.insert(c, Velocity(vec2(0., 1.)));
+
+        for (position, velocity) in ecs.query::<(&mut Vec2, &Velocity)>() {
+            *position += velocity.0;
+        }
+        assert_eq!(ecs.components.positions.get(&a), Some(&vec2(1., 0.)));
+        assert_eq!(ecs.components.positions.get(&b), Some(&Vec2::ZERO));
+        assert_eq!(ecs.components.positions.get(&c), Some(&vec2(0., 1.)));
+
+        let with_velocity = ecs
+            .query_ref::<(Entity, Option<&Velocity>)>()
+            .map(|(e, velocity)| (e, velocity.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(with_velocity, [(a, true), (b, false), (c, true)]);
+    }
+}
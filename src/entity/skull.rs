@@ -6,11 +6,11 @@ use super::{
     animated_sprite::{AnimatedSprite, Animation},
     entities::Ecs,
     entity_id::Entity,
-    tags::DespawnOnAnimEnd,
+    tags::{DespawnOnAnimEnd, RoomEntity},
 };
 
 pub fn spawn_skull(data: &mut GameData, ecs: &mut Ecs, position: Vec2) -> Entity {
-    let id = data.new_entity();
+    let id = ecs.spawn();
 
     let indexed_sprite = IndexedSprite::new(data, "skull", 16, vec2(8., 8.));
     let sprite = AnimatedSprite::new(
@@ -25,8 +25,7 @@ pub fn spawn_skull(data: &mut GameData, ecs: &mut Ecs, position: Vec2) -> Entity
     ecs.components
         .despawn_on_anim_end
         .insert(id, DespawnOnAnimEnd);
-    ecs.components.room_entity.insert(id, ());
+    ecs.components.room_entity.insert(id, RoomEntity);
 
-    ecs.entities.push(id);
     id
 }
@@ -0,0 +1,157 @@
+use super::entity_id::Entity;
+
+/// Component storage keyed by entity index.
+///
+/// Values are packed densely so iterating a component touches only the
+/// entities that actually have it. The sparse array maps an entity index to
+/// its slot in the dense arrays, and lookups compare the stored entity so a
+/// handle from an older generation never reads a newer entity's data.
+pub struct SparseSet<T> {
+    sparse: Vec<Option<usize>>,
+    dense: Vec<Entity>,
+    data: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self {
+            sparse: vec![],
+            dense: vec![],
+            data: vec![],
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    fn dense_index(&self, entity: &Entity) -> Option<usize> {
+        lookup(&self.sparse, &self.dense, *entity)
+    }
+
+    pub fn insert(&mut self, entity: Entity, value: T) -> Option<T> {
+        let sparse_index = entity.index as usize;
+        if sparse_index >= self.sparse.len() {
+            self.sparse.resize(sparse_index + 1, None);
+        }
+
+        if let Some(index) = self.sparse[sparse_index] {
+            // Either the same entity or a stale generation occupying the slot
+            self.dense[index] = entity;
+            return Some(std::mem::replace(&mut self.data[index], value));
+        }
+
+        self.sparse[sparse_index] = Some(self.dense.len());
+        self.dense.push(entity);
+        self.data.push(value);
+        None
+    }
+
+    pub fn remove(&mut self, entity: &Entity) -> Option<T> {
+        let index = self.dense_index(entity)?;
+
+        self.sparse[entity.index as usize] = None;
+        self.dense.swap_remove(index);
+        let value = self.data.swap_remove(index);
+        if let Some(moved) = self.dense.get(index) {
+            self.sparse[moved.index as usize] = Some(index);
+        }
+        Some(value)
+    }
+
+    pub fn get(&self, entity: &Entity) -> Option<&T> {
+        let index = self.dense_index(entity)?;
+        Some(&self.data[index])
+    }
+
+    pub fn get_mut(&mut self, entity: &Entity) -> Option<&mut T> {
+        let index = self.dense_index(entity)?;
+        Some(&mut self.data[index])
+    }
+
+    pub fn contains(&self, entity: &Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// The entities in the set, in storage order.
+    pub fn entities(&self) -> &[Entity] {
+        &self.dense
+    }
+
+    /// Borrows the lookup tables shared and the values mutably, so a query can
+    /// walk `dense` while handing out `&mut T` for distinct entities.
+    pub(super) fn split_mut(&mut self) -> (&[Option<usize>], &[Entity], &mut [T]) {
+        (&self.sparse, &self.dense, &mut self.data)
+    }
+
+    pub(super) fn split(&self) -> (&[Option<usize>], &[Entity], &[T]) {
+        (&self.sparse, &self.dense, &self.data)
+    }
+}
+
+pub(super) fn lookup(sparse: &[Option<usize>], dense: &[Entity], entity: Entity) -> Option<usize> {
+    let index = (*sparse.get(entity.index as usize)?)?;
+    if dense[index] == entity {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(index: u32, generation: u32) -> Entity {
+        Entity { index, generation }
+    }
+
+    #[test]
+    fn swap_remove_keeps_the_indices_consistent() {
+        let mut set = SparseSet::default();
+        for index in 0..4 {
+            set.insert(entity(index, 0), index);
+        }
+
+        assert_eq!(set.remove(&entity(1, 0)), Some(1));
+        // The last entity took the removed one's slot
+        assert_eq!(set.entities(), [entity(0, 0), entity(3, 0), entity(2, 0)]);
+        for (slot, e) in set.entities().iter().enumerate() {
+            assert_eq!(set.sparse[e.index as usize], Some(slot));
+            assert_eq!(set.get(e), Some(&e.index));
+        }
+        assert_eq!(set.sparse[1], None);
+        assert!(!set.contains(&entity(1, 0)));
+
+        // Removing the last slot moves nothing
+        assert_eq!(set.remove(&entity(2, 0)), Some(2));
+        assert_eq!(set.entities(), [entity(0, 0), entity(3, 0)]);
+        assert_eq!(set.get(&entity(3, 0)), Some(&3));
+    }
+
+    #[test]
+    fn ignores_other_generations_of_an_index() {
+        let mut set = SparseSet::default();
+        set.insert(entity(2, 1), "new");
+
+        assert_eq!(set.get(&entity(2, 0)), None);
+        assert!(!set.contains(&entity(2, 0)));
+        assert_eq!(set.remove(&entity(2, 0)), None);
+        assert_eq!(set.get(&entity(2, 1)), Some(&"new"));
+        // Out of range indices aren't in the set either
+        assert_eq!(set.get(&entity(9, 0)), None);
+    }
+
+    #[test]
+    fn inserting_a_new_generation_replaces_a_stale_one() {
+        let mut set = SparseSet::default();
+        set.insert(entity(0, 0), "old");
+
+        assert_eq!(set.insert(entity(0, 1), "new"), Some("old"));
+        assert_eq!(set.entities(), [entity(0, 1)]);
+        assert_eq!(set.get(&entity(0, 0)), None);
+        assert_eq!(set.get(&entity(0, 1)), Some(&"new"));
+    }
+}
@@ -1,28 +1,47 @@
 use macroquad::prelude::*;
 
-use crate::game_data::GameData;
+use crate::{map::spawn_point::SpawnPointDef, timer::Timer};
 
-use super::{entities::Ecs, entity_id::Entity};
+use super::{entities::Ecs, entity_id::Entity, tags::RoomEntity};
 
 #[derive(Clone)]
 pub struct Spawner {
     pub active: bool,
     pub last_spawn_time: f64,
+    /// Prefab names of the enemies it may spawn, any when empty.
+    pub enemies: Vec<String>,
+    pub wave: usize,
+    /// Runs once the spawner's wave started, then again between the enemies
+    /// of a count.
+    pub delay_timer: Timer,
+    /// Enemies left to spawn, `None` while taking them from the room's
+    /// difficulty budget.
+    pub count: Option<usize>,
 }
 
-pub fn spawn_spawner(data: &mut GameData, position: Vec2, ecs: &mut Ecs) -> Entity {
-    let id = data.new_entity();
+impl Spawner {
+    pub fn allows(&self, enemy: &str) -> bool {
+        self.enemies.is_empty() || self.enemies.iter().any(|name| name == enemy)
+    }
+}
+
+/// Spawners of a wave started by a trigger volume wait for it inactive.
+pub fn spawn_spawner(def: &SpawnPointDef, triggered: bool, ecs: &mut Ecs) -> Entity {
+    let id = ecs.spawn();
 
-    ecs.components.positions.insert(id, position);
+    ecs.components.positions.insert(id, def.position);
 
     let spawner = Spawner {
-        active: true,
+        active: !triggered,
         last_spawn_time: get_time(),
+        enemies: def.enemies.clone(),
+        wave: def.wave,
+        delay_timer: Timer::new(def.delay, false),
+        count: def.count,
     };
     ecs.components.spawners.insert(id, spawner);
 
-    ecs.entities.push(id);
-    ecs.components.room_entity.insert(id, ());
+    ecs.components.room_entity.insert(id, RoomEntity);
 
     id
 }
@@ -1,6 +1,9 @@
+use macroquad::math::Vec2;
+use nanoserde::DeJson;
+
 use crate::timer::Timer;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, DeJson)]
 pub enum EntityType {
     Player,
     Enemy,
@@ -10,6 +13,8 @@ pub enum EntityType {
 pub struct DamageOnCollision {
     pub source: EntityType,
     pub damage: f32,
+    /// Impulse given to the target, away from the source.
+    pub knockback: f32,
 }
 
 pub struct Health {
@@ -23,3 +28,46 @@ pub struct Damageable {
 
 pub struct DespawnOnAnimEnd;
 pub struct DespawnOnHit(pub EntityType);
+
+pub struct Velocity(pub Vec2);
+/// Lets an entity be pushed around on top of its regular `Velocity`.
+/// Impulses are divided by the mass, so heavy entities barely move, and the
+/// resulting knockback slows down by `friction` per second.
+pub struct PhysicsBody {
+    pub mass: f32,
+    pub friction: f32,
+    pub impulse: Vec2,
+    pub knockback: Vec2,
+}
+
+impl PhysicsBody {
+    pub fn new(mass: f32, friction: f32) -> Self {
+        Self {
+            mass,
+            friction,
+            impulse: Vec2::ZERO,
+            knockback: Vec2::ZERO,
+        }
+    }
+
+    /// Applied at the start of the next physics step.
+    pub fn apply_impulse(&mut self, impulse: Vec2) {
+        self.impulse += impulse;
+    }
+}
+
+/// Velocity an entity keeps sliding with while it stands on ice.
+pub struct IceSlide(pub Vec2);
+
+/// Position at the start of the current step, sprites are drawn between it
+/// and the current position.
+pub struct PreviousPosition(pub Vec2);
+
+pub struct FlipToPlayer;
+pub struct PlayerEntity;
+pub struct EnemyEntity;
+pub struct RoomEntity;
+
+pub struct LayerOffset(pub i8);
+pub struct Ball(pub usize);
+pub struct AberrationIncrease(pub f32);
@@ -0,0 +1,40 @@
+use macroquad::prelude::*;
+
+use crate::{
+    map::trigger::{TriggerAction, TriggerVolumeDef},
+    systems::collision::{Collider, ColliderType},
+};
+
+use super::{
+    entities::Ecs,
+    entity_id::Entity,
+    tags::{RoomEntity, Velocity},
+};
+
+pub struct TriggerVolume {
+    pub on_enter: Vec<TriggerAction>,
+    pub on_exit: Vec<TriggerAction>,
+    pub once: bool,
+}
+
+pub fn spawn_trigger_volume(def: &TriggerVolumeDef, ecs: &mut Ecs) -> Entity {
+    let id = ecs.spawn();
+
+    ecs.components.positions.insert(id, def.position);
+    // Only colliders with a velocity take part in the physics step
+    ecs.components.velocities.insert(id, Velocity(Vec2::ZERO));
+    ecs.components
+        .colliders
+        .insert(id, Collider::new(def.shape.clone(), ColliderType::Trigger));
+
+    let trigger = TriggerVolume {
+        on_enter: def.on_enter.clone(),
+        on_exit: def.on_exit.clone(),
+        once: def.once,
+    };
+    ecs.components.trigger_volumes.insert(id, trigger);
+
+    ecs.components.room_entity.insert(id, RoomEntity);
+
+    id
+}
@@ -1,4 +1,5 @@
 use macroquad::prelude::rand;
+use nanoserde::{DeJson, SerJson};
 
 use crate::{
     game_data::GameData,
@@ -49,7 +50,7 @@ impl Upgrade {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, SerJson, DeJson)]
 pub enum ItemUpgrade {
     Hp(f32),
     AnomalyBig,
@@ -72,7 +73,7 @@ impl ItemUpgrade {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, SerJson, DeJson)]
 pub enum CommonUpgrade {
     MaxHp(u8),
     MoveSpeed(f32),
@@ -116,14 +117,14 @@ impl WeaponUpgrade {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, SerJson, DeJson)]
 pub enum LauncherUpgrade {
     FireRate(f32),
     Damage(f32),
     DoubleBullet,
 }
 
-#[derive(Clone)]
+#[derive(Clone, SerJson, DeJson)]
 pub enum BallsUpgrade {
     Amount(usize),
     Damage(f32),
@@ -131,7 +132,7 @@ pub enum BallsUpgrade {
     Split,
 }
 
-#[derive(Clone)]
+#[derive(Clone, SerJson, DeJson)]
 pub enum DashUpgrade {
     Damage(f32),
     TimerDecrease(f32),
@@ -205,6 +206,7 @@ impl DashUpgrade {
     }
 }
 
+#[derive(Clone, SerJson, DeJson)]
 pub struct Upgrades {
     item_upgrades: Vec<ItemUpgrade>,
     common_upgrades: Vec<CommonUpgrade>,
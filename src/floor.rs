@@ -0,0 +1,255 @@
+use std::collections::{HashMap, VecDeque};
+
+use macroquad::{
+    math::{vec2, Vec2},
+    rand,
+};
+use nanoserde::{DeJson, SerJson};
+
+use crate::map::map::Map;
+
+/// Rooms with enemies on a floor, besides the start, treasure and boss rooms.
+const COMBAT_ROOMS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SerJson, DeJson)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    /// Directions by the values of the `door` property in Tiled.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "north" => Some(Direction::North),
+            "east" => Some(Direction::East),
+            "south" => Some(Direction::South),
+            "west" => Some(Direction::West),
+            _ => None,
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// Unit vector pointing out of the room.
+    pub fn vec(&self) -> Vec2 {
+        match self {
+            Direction::North => vec2(0., -1.),
+            Direction::East => vec2(1., 0.),
+            Direction::South => vec2(0., 1.),
+            Direction::West => vec2(-1., 0.),
+        }
+    }
+
+    fn offset(&self) -> (i32, i32) {
+        let vec = self.vec();
+        (vec.x as i32, vec.y as i32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SerJson, DeJson)]
+pub enum RoomKind {
+    Start,
+    Combat,
+    /// No enemies, only a few pickups.
+    Treasure,
+    Boss,
+}
+
+impl RoomKind {
+    /// Room kinds by the names used in the `room_types` map property.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "start" => Some(RoomKind::Start),
+            "combat" => Some(RoomKind::Combat),
+            "treasure" => Some(RoomKind::Treasure),
+            "boss" => Some(RoomKind::Boss),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, SerJson, DeJson)]
+pub struct FloorRoom {
+    pub kind: RoomKind,
+    pub map_index: usize,
+    /// Position on the floor's grid, rooms connected by a door are next to
+    /// each other in the door's direction.
+    pub cell: (i32, i32),
+    /// Index of the neighbour behind the door in each direction.
+    pub doors: HashMap<Direction, usize>,
+    pub visited: bool,
+    pub cleared: bool,
+}
+
+/// The rooms of a floor and how their doors connect them. Saved with the run,
+/// so a continued run picks up on the same floor.
+#[derive(Clone, SerJson, DeJson)]
+pub struct Floor {
+    pub rooms: Vec<FloorRoom>,
+    pub current: usize,
+}
+
+impl Floor {
+    /// Grows a tree of rooms on a grid from the start room, then hangs the
+    /// boss room off the room furthest from the start and the treasure room
+    /// off any other. Every room gets a map of the pool made for its kind and
+    /// depth that has a door in each direction the room needs one.
+    pub fn generate(maps: &[Map]) -> Self {
+        let mut floor = Floor {
+            rooms: vec![],
+            current: 0,
+        };
+        floor.rooms.push(FloorRoom::new(RoomKind::Start, (0, 0)));
+
+        while floor.rooms.len() < COMBAT_ROOMS + 1 {
+            let from = rand::gen_range(0, floor.rooms.len());
+            floor.add_neighbour(from, RoomKind::Combat);
+        }
+
+        let distances = floor.distances(0);
+        let mut furthest = (0..floor.rooms.len()).collect::<Vec<_>>();
+        furthest.sort_by_key(|room| std::cmp::Reverse(distances[*room]));
+        for room in furthest {
+            if floor.add_neighbour(room, RoomKind::Boss).is_some() {
+                break;
+            }
+        }
+
+        loop {
+            let from = rand::gen_range(1, floor.rooms.len());
+            if floor.rooms[from].kind == RoomKind::Combat
+                && floor.add_neighbour(from, RoomKind::Treasure).is_some()
+            {
+                break;
+            }
+        }
+
+        let depths = floor.distances(0);
+        for (room, depth) in floor.rooms.iter_mut().zip(depths) {
+            room.map_index = pick_map(maps, room, depth);
+        }
+
+        floor
+    }
+
+    pub fn current(&self) -> &FloorRoom {
+        &self.rooms[self.current]
+    }
+
+    pub fn enter(&mut self, room: usize) {
+        self.current = room;
+        self.rooms[room].visited = true;
+    }
+
+    /// The boss room opens early once everything else on the floor is
+    /// cleared, so a floor can't run out of rooms before the boss.
+    pub fn rest_cleared(&self) -> bool {
+        self.rooms
+            .iter()
+            .all(|room| room.kind == RoomKind::Boss || room.cleared)
+    }
+
+    /// Connects a new room to a free cell next to `from`, `None` when all of
+    /// them are taken.
+    fn add_neighbour(&mut self, from: usize, kind: RoomKind) -> Option<usize> {
+        let (x, y) = self.rooms[from].cell;
+        let free = Direction::ALL
+            .into_iter()
+            .filter(|dir| {
+                let (dx, dy) = dir.offset();
+                !self.rooms.iter().any(|room| room.cell == (x + dx, y + dy))
+            })
+            .collect::<Vec<_>>();
+        if free.is_empty() {
+            return None;
+        }
+
+        let dir = free[rand::gen_range(0, free.len())];
+        let (dx, dy) = dir.offset();
+        let index = self.rooms.len();
+        let mut room = FloorRoom::new(kind, (x + dx, y + dy));
+        room.doors.insert(dir.opposite(), from);
+        self.rooms.push(room);
+        self.rooms[from].doors.insert(dir, index);
+        Some(index)
+    }
+
+    /// Number of doors between `from` and every room.
+    fn distances(&self, from: usize) -> Vec<usize> {
+        let mut distances = vec![usize::MAX; self.rooms.len()];
+        let mut open = VecDeque::from([from]);
+        distances[from] = 0;
+        while let Some(room) = open.pop_front() {
+            for next in self.rooms[room].doors.values() {
+                if distances[*next] == usize::MAX {
+                    distances[*next] = distances[room] + 1;
+                    open.push_back(*next);
+                }
+            }
+        }
+        distances
+    }
+}
+
+impl FloorRoom {
+    fn new(kind: RoomKind, cell: (i32, i32)) -> Self {
+        Self {
+            kind,
+            map_index: 0,
+            cell,
+            doors: HashMap::new(),
+            visited: false,
+            cleared: false,
+        }
+    }
+}
+
+/// A random map that fits the room, weighted by the maps' `weight`.
+fn pick_map(maps: &[Map], room: &FloorRoom, depth: usize) -> usize {
+    let candidates = maps
+        .iter()
+        .enumerate()
+        .filter(|(_, map)| {
+            map.meta.room_kinds.contains(&room.kind)
+                && map.meta.min_depth <= depth
+                && room.doors.keys().all(|dir| map.doors.contains_key(dir))
+        })
+        .map(|(index, map)| (index, map.meta.weight))
+        .collect::<Vec<_>>();
+
+    let total_weight = candidates.iter().map(|(_, weight)| weight).sum::<f32>();
+    if candidates.is_empty() || total_weight <= 0. {
+        panic!(
+            "no map for a {:?} room at depth {} with doors {:?}",
+            room.kind,
+            depth,
+            room.doors.keys().collect::<Vec<_>>()
+        );
+    }
+
+    let mut roll = rand::gen_range(0., total_weight);
+    for (index, weight) in &candidates {
+        if roll < *weight {
+            return *index;
+        }
+        roll -= weight;
+    }
+    candidates[candidates.len() - 1].0
+}
@@ -4,23 +4,37 @@ use macroquad::{audio::Sound, prelude::*};
 
 use crate::{
     entity::{
-        entities::Ecs, entity_id::Entity, mirituhg::spawn_mirituhg, player::spawn_player,
-        spawner::spawn_spawner, upgrades::Upgrades,
+        door::spawn_door,
+        entities::Ecs,
+        pickup::spawn_pickup,
+        player::{spawn_player, PlayerData},
+        prefab::{spawn_prefab, Prefab},
+        spawner::spawn_spawner,
+        tags::Health,
+        trigger::spawn_trigger_volume,
+        upgrades::Upgrades,
     },
+    floor::{Direction, Floor, RoomKind},
     game_state::GameState,
     input_manager::InputManager,
     items::weapon::{Launcher, Weapon},
-    map::map::Map,
+    map::{map::Map, nav::Navigation, trigger::triggered_waves},
     rand_utils::rand_dir,
-    room::Room,
+    room::{Item, Room},
+    save::has_save,
     settings::GameSettings,
+    time::Time,
     timer::Timer,
     ui::{
-        death_screen::DeathScreen, end_game_screen::EndGameScreen, screen_dimmer::ScreenDimmer,
-        ui_data::UIData,
+        death_screen::DeathScreen, dialogue::Dialogue, end_game_screen::EndGameScreen,
+        screen_dimmer::ScreenDimmer, ui_data::UIData,
     },
 };
 
+/// How far into the room the player starts when coming through a door, so
+/// they don't walk right back out.
+const DOOR_ENTRY_DISTANCE: f32 = 16.;
+
 pub enum GameMaterial {
     Aberration(Material),
     Color(Material),
@@ -32,7 +46,7 @@ pub struct Graphics {
     pub noise1_texture: Texture2D,
     pub noise2_texture: Texture2D,
     pub materials: HashMap<String, GameMaterial>,
-    pub textures: HashMap<&'static str, Texture2D>,
+    pub textures: HashMap<String, Texture2D>,
 }
 
 pub struct Audio {
@@ -49,6 +63,25 @@ pub struct Audio {
     pub music1: Sound,
 }
 
+impl Audio {
+    /// Sounds by the names used in the maps.
+    pub fn get(&self, name: &str) -> Option<&Sound> {
+        match name {
+            "ui_switch" => Some(&self.ui_switch),
+            "shoot" => Some(&self.shoot),
+            "death" => Some(&self.death),
+            "death2" => Some(&self.death2),
+            "spawn" => Some(&self.spawn),
+            "kill" => Some(&self.kill),
+            "confirm" => Some(&self.confirm),
+            "confirm2" => Some(&self.confirm2),
+            "hit" => Some(&self.hit),
+            "hit2" => Some(&self.hit2),
+            _ => None,
+        }
+    }
+}
+
 pub struct ScreenShake {
     pub distance: f32,
     pub camera_offset: Vec2,
@@ -75,27 +108,34 @@ impl ScreenShake {
 }
 
 pub struct GameData {
-    pub entity_index: u64,
     pub state: GameState,
     pub settings: GameSettings,
     pub ui: UIData,
     pub graphics: Graphics,
+    pub prefabs: HashMap<String, Prefab>,
     pub audio: Audio,
     pub input: InputManager,
     pub camera: Camera2D,
     pub debug_collisions: bool,
+    pub debug_spatial_hash: bool,
     pub show_fps: bool,
     pub weapon: Weapon,
     pub current_room: Room,
     pub next_room: Option<Room>,
     pub maps: Vec<Map>,
+    pub floor: Floor,
+    pub navigation: Navigation,
     pub screen_dimmer: ScreenDimmer,
     pub map_change_requested: bool,
     pub paused: bool,
     pub pause_timer: Timer,
     pub show_pause_menu: bool,
+    /// The floor map replaces the pause menu while shown.
+    pub show_floor_map: bool,
+    pub quit_requested: bool,
     pub death_screen: DeathScreen,
     pub end_game_screen: EndGameScreen,
+    pub dialogue: Dialogue,
     pub dead: bool,
     pub previous_window_size: (f32, f32),
     pub game_completed: bool,
@@ -103,45 +143,56 @@ pub struct GameData {
     pub screen_shake: ScreenShake,
     pub completed_rooms: usize,
     pub upgrades: Upgrades,
+    pub has_save: bool,
+    pub time: Time,
 }
 
 impl GameData {
     pub fn new(
-        initial_entity_index: u64,
         settings: GameSettings,
         ui_data: UIData,
         maps: Vec<Map>,
         graphics: Graphics,
+        prefabs: HashMap<String, Prefab>,
         audio: Audio,
         death_texture: Texture2D,
         end_game_texture: Texture2D,
     ) -> Self {
         let camera = Camera2D::default();
+        let floor = Floor::generate(&maps);
+        let current_room = Room::new(0, 0., &[], &prefabs);
+        let has_save = has_save(&maps);
         Self {
-            entity_index: initial_entity_index,
             settings,
             state: GameState::default(),
             ui: ui_data,
             graphics,
+            prefabs,
             audio,
             input: InputManager::new(),
             camera,
             debug_collisions: false,
+            debug_spatial_hash: false,
             #[cfg(debug_assertions)]
             show_fps: true,
             #[cfg(not(debug_assertions))]
             show_fps: false,
             weapon: Weapon::Launcher(Launcher::new()),
-            current_room: Room::new(0, 0.),
+            current_room,
             next_room: None,
             maps,
+            floor,
+            navigation: Navigation::default(),
             screen_dimmer: ScreenDimmer::new(),
             map_change_requested: false,
             paused: false,
             pause_timer: Timer::new(1., false),
             show_pause_menu: false,
+            show_floor_map: false,
+            quit_requested: false,
             death_screen: DeathScreen::new(death_texture),
             end_game_screen: EndGameScreen::new(end_game_texture),
+            dialogue: Dialogue::new(),
             dead: true,
             previous_window_size: (screen_width(), screen_height()),
             game_completed: false,
@@ -149,33 +200,32 @@ impl GameData {
             screen_shake: ScreenShake::new(),
             completed_rooms: 0,
             upgrades: Upgrades::new(),
+            has_save,
+            time: Time::new(),
         }
     }
 
     pub fn reset(&mut self) {
         self.state = GameState::Intro;
         self.weapon = Weapon::Launcher(Launcher::new());
-        self.current_room = Room::new(0, 3.);
+        self.current_room = Room::new(0, 0., &[], &self.prefabs);
         self.next_room = None;
+        self.navigation.clear();
         self.dead = false;
         self.completed_rooms = 0;
+        self.dialogue = Dialogue::new();
     }
 
-    pub fn new_entity(&mut self) -> Entity {
-        self.entity_index += 1;
-        Entity(self.entity_index)
-    }
-
-    pub fn update(&mut self) {
-        self.pause_timer.update();
+    pub fn update(&mut self, dt: f32) {
+        self.pause_timer.update(dt);
         self.input.gamepads.poll();
         self.update_camera();
 
         let shake = &mut self.screen_shake;
         self.camera.target = vec2(360. / 2., 240. / 2.) + shake.camera_offset;
         if !self.paused {
-            shake.timer.update();
-            shake.event_timer.update();
+            shake.timer.update(dt);
+            shake.event_timer.update(dt);
             if shake.event_timer.progress() > 0. && shake.timer.completed() {
                 shake.timer.reset();
                 shake.camera_offset = rand_dir() * shake.event_timer.progress() * shake.distance;
@@ -210,73 +260,121 @@ impl GameData {
 
     pub fn spawn_map_entities(&mut self, ecs: &mut Ecs) -> Vec2 {
         let mut player_pos = Vec2::ZERO;
-        let mut spawner_positions = vec![];
         let mut spawn_boss = false;
         for (_, layer) in &self.current_map().tiled_map.layers {
             for object in &layer.objects {
                 let object_pos = vec2(object.world_x + 4., object.world_y - 4.);
-                // if let Some(_door_dir) = object.properties.get("door") {
-                //     spawn_door(self, object_pos, ecs);
-                // }
                 if let Some(_door_dir) = object.properties.get("player") {
                     player_pos = object_pos;
                 }
-                if let Some(_) = object.properties.get("spawn") {
-                    spawner_positions.push(object_pos);
-                }
                 if let Some(_) = object.properties.get("boss") {
                     spawn_boss = true;
                 }
             }
         }
 
+        if let Some(entrance) = self.current_room.entrance {
+            if let Some(door_pos) = self.current_map().doors.get(&entrance) {
+                player_pos = *door_pos - entrance.vec() * DOOR_ENTRY_DISTANCE;
+            }
+        }
+
+        for (dir, room) in &self.floor.current().doors {
+            if let Some(door_pos) = self.current_map().doors.get(dir) {
+                spawn_door(*door_pos, *dir, *room, ecs);
+            }
+        }
+
         if spawn_boss {
-            spawn_mirituhg(self, vec2(180., 120.), ecs);
+            spawn_prefab(self, ecs, "mirituhg", vec2(180., 120.));
         }
 
-        for pos in spawner_positions {
-            spawn_spawner(self, pos, ecs);
+        let items = self.current_room.items_to_spawn.clone();
+        let item_positions = self
+            .current_map()
+            .spawn_points
+            .iter()
+            .map(|spawn_point| spawn_point.position)
+            .collect::<Vec<_>>();
+        for (item, pos) in items.iter().zip(item_positions) {
+            spawn_pickup(self, pos, ecs, item.pickup());
+        }
+
+        // Nothing to fight in treasure rooms and rooms cleared before
+        let floor_room = self.floor.current();
+        if floor_room.kind != RoomKind::Treasure && !floor_room.cleared {
+            let map = self.current_map();
+            let triggered = triggered_waves(&map.trigger_volumes);
+            for spawn_point in &map.spawn_points {
+                spawn_spawner(spawn_point, triggered.contains(&spawn_point.wave), ecs);
+            }
+        }
+
+        for volume in &self.current_map().trigger_volumes {
+            spawn_trigger_volume(volume, ecs);
         }
 
         player_pos
     }
 
-    pub fn next_room(&mut self, ecs: &mut Ecs) {
-        self.current_room.despawn(ecs);
-
-        let map_index = match self.completed_rooms {
-            0 => 1,
-            _ => rand::gen_range(1, self.maps.len()),
-        };
+    /// Generates a new floor and moves to its start room.
+    pub fn start_floor(&mut self, ecs: &mut Ecs) {
+        self.floor = Floor::generate(&self.maps);
+        self.enter_room(ecs, 0, None);
+    }
 
-        let mut new_room = Room::new(map_index, 2. + 3. * self.completed_rooms as f32);
+    /// Moves to a room of the floor once the screen is dimmed. `entrance` is
+    /// the door of that room the player comes in through.
+    pub fn enter_room(&mut self, ecs: &mut Ecs, floor_index: usize, entrance: Option<Direction>) {
+        self.current_room.despawn(ecs);
 
-        new_room.available_upgrades = if self.completed_rooms == 0 {
-            Upgrades::weapon_selection()
+        let floor_room = &self.floor.rooms[floor_index];
+        let mut new_room = if floor_room.cleared {
+            // Nothing left to do in there
+            let mut room = Room::new(floor_room.map_index, 0., &[], &self.prefabs);
+            room.upgrade_chosen = true;
+            room.entities_spawned = true;
+            room.completed = true;
+            room
         } else {
-            let players = ecs.check_components(|e, comps| comps.player_data.contains_key(e));
-            let player_data = ecs.components.player_data.get(&players[0]).unwrap();
-            let up_data = player_data.get_upgraded_data();
-            let health = ecs.components.health.get(&players[0]).unwrap();
-
-            if player_data.aberration >= 1. {
-                // TODO: boss room
-                new_room.map_index = 0;
-            }
+            let mut room = match floor_room.kind {
+                RoomKind::Treasure => {
+                    let mut room = Room::new(floor_room.map_index, 0., &[], &self.prefabs);
+                    room.items_to_spawn = vec![Item::SuperHealth, Item::AberrationRelief];
+                    room
+                }
+                _ => Room::new(
+                    floor_room.map_index,
+                    2. + 3. * self.completed_rooms as f32,
+                    &self.maps[floor_room.map_index].spawn_points,
+                    &self.prefabs,
+                ),
+            };
+
+            room.available_upgrades = if self.completed_rooms == 0 {
+                Upgrades::weapon_selection()
+            } else {
+                let (player_data, health) =
+                    ecs.query_ref::<(&PlayerData, &Health)>().next().unwrap();
+                let up_data = player_data.get_upgraded_data();
 
-            self.upgrades.generate_upgrades(
-                &self.weapon,
-                up_data.max_hp as f32 - health.hp,
-                player_data.aberration,
-            )
+                self.upgrades.generate_upgrades(
+                    &self.weapon,
+                    up_data.max_hp as f32 - health.hp,
+                    player_data.aberration,
+                )
+            };
+            self.completed_rooms += 1;
+            room
         };
+        new_room.floor_index = floor_index;
+        new_room.entrance = entrance;
 
         self.next_room = Some(new_room);
         self.map_change_requested = true;
         self.screen_dimmer.dim();
         self.paused = true;
         self.pause_timer.reset();
-        self.completed_rooms += 1;
     }
 }
 
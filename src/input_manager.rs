@@ -8,7 +8,7 @@ use macroquad::{
     window::screen_height,
 };
 
-use crate::entity::entities::Ecs;
+use crate::entity::{entities::Ecs, player::PlayerData};
 
 pub struct InputManager {
     pub gamepads: Gamepads,
@@ -23,6 +23,7 @@ pub enum Action {
     Down,
     Confirm,
     Pause,
+    Map,
 }
 
 impl InputManager {
@@ -42,12 +43,7 @@ impl InputManager {
         let mouse_delta = mouse_pos - self.last_mouse_pos;
         if mouse_delta.length_squared() > 0.001 {
             self.last_mouse_pos = mouse_pos;
-            let players = ecs.check_components(|e, comps| {
-                comps.player_data.contains_key(e) && comps.positions.contains_key(e)
-            });
-
-            for player_e in &players {
-                let player_pos = ecs.components.positions.get(player_e).unwrap();
+            for (_, player_pos) in ecs.query_ref::<(&PlayerData, &Vec2)>() {
                 let mouse_pos = camera.screen_to_world(mouse_pos);
                 let delta = (mouse_pos - *player_pos).normalize();
                 input_dir = Some(delta);
@@ -191,6 +187,11 @@ impl InputManager {
                     return true;
                 }
             }
+            Action::Map => {
+                if is_key_pressed(KeyCode::M) || is_key_pressed(KeyCode::Tab) {
+                    return true;
+                }
+            }
         }
 
         if let Some(gamepad) = gamepad {
@@ -225,6 +226,11 @@ impl InputManager {
                         return true;
                     }
                 }
+                Action::Map => {
+                    if gamepad.is_just_pressed(Button::LeftCenterCluster) {
+                        return true;
+                    }
+                }
             }
         }
 
@@ -267,6 +273,11 @@ impl InputManager {
                     return true;
                 }
             }
+            Action::Map => {
+                if is_key_down(KeyCode::M) || is_key_down(KeyCode::Tab) {
+                    return true;
+                }
+            }
         }
 
         if let Some(gamepad) = gamepad {
@@ -301,6 +312,11 @@ impl InputManager {
                         return true;
                     }
                 }
+                Action::Map => {
+                    if gamepad.is_currently_pressed(Button::LeftCenterCluster) {
+                        return true;
+                    }
+                }
             }
         }
 
@@ -340,6 +356,11 @@ impl InputManager {
                     return true;
                 }
             }
+            Action::Map => {
+                if is_key_released(KeyCode::M) || is_key_released(KeyCode::Tab) {
+                    return true;
+                }
+            }
         }
 
         if let Some(gamepad) = gamepad {
@@ -374,6 +395,11 @@ impl InputManager {
                         return true;
                     }
                 }
+                Action::Map => {
+                    if gamepad.is_just_released(Button::LeftCenterCluster) {
+                        return true;
+                    }
+                }
             }
         }
 
@@ -1,6 +1,6 @@
 use std::f32::consts::TAU;
 
-use macroquad::{math::Vec2, time::get_frame_time};
+use macroquad::math::Vec2;
 
 use crate::{
     entity::upgrades::{BallsUpgrade, DashUpgrade, LauncherUpgrade},
@@ -95,10 +95,10 @@ impl Balls {
         }
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, dt: f32) {
         let data = self.get_upgraded_data();
-        self.rotation_progress += get_frame_time() * (TAU * data.rotation_speed);
-        self.ball_spawn_timer.update();
+        self.rotation_progress += dt * (TAU * data.rotation_speed);
+        self.ball_spawn_timer.update(dt);
         if self.ball_spawn_timer.just_completed() {
             self.buffered_spawns = (self.buffered_spawns + 1).min(data.amount);
         }
@@ -163,10 +163,10 @@ impl Dash {
         }
     }
 
-    pub fn update(&mut self) {
-        self.dash_timer.update();
-        self.dashing_timer.update();
-        self.shadow_timer.update();
+    pub fn update(&mut self, dt: f32) {
+        self.dash_timer.update(dt);
+        self.dashing_timer.update(dt);
+        self.shadow_timer.update(dt);
     }
 
     pub fn get_upgraded_data(&self) -> DashData {
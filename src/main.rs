@@ -2,44 +2,31 @@ use std::collections::HashMap;
 
 use entity::{
     entities::Ecs,
-    entity_id::Entity,
-    events::{DamageEvent, DeathEvent},
-    mirituhg::spawn_mirituhg,
-    player::spawn_player,
-    upgrades::{CommonUpgrade, ItemUpgrade, Upgrade, WeaponUpgrade},
+    player::{spawn_player, PlayerData},
+    prefab::load_prefabs,
 };
 use fps_counter::FPSCounter;
 use game_data::{Audio, GameMaterial};
 use game_state::GameState;
-use items::weapon::{Balls, Dash, Launcher, Weapon, WeaponType};
 use macroquad::{
     audio::{self, play_sound, set_sound_volume},
     miniquad::window::set_mouse_cursor,
     prelude::*,
 };
-use macroquad_tiled::load_map;
+use save::{continue_run, delete_save, load_save, save_on_quit};
+use schedule::{Schedule, Stage, System};
 use settings::{GameSettings, WindowSize};
 use sprite::{
     aberration_material::create_aberration_material, flash_material::create_sprite_color_material,
 };
-use systems::{
-    collision::draw_colliders,
-    damageable::{
-        apply_damage, damage_on_collision, despawn_on_collision, flash_on_damage, handle_death,
-        kill_entities, update_damageables,
-    },
-    enemy::update_enemies,
-    movement::move_entities,
-    player::update_player,
-    spawn::spawn_creatures,
-    sprite::{draw_animated_sprites, update_animated_sprites},
-    timer::update_timers,
-    weapon::update_weapon,
-};
+use systems::schedule::add_game_systems;
+#[cfg(debug_assertions)]
+use ui::inspector::Inspector;
 use ui::{
     hud::{create_aberration_meter_material, AberrationMeter, HudHearts},
     icon,
-    intro_screen::IntroScreen,
+    intro_screen::{IntroChoice, IntroScreen},
+    minimap::{floor_map, Minimap},
     mirituhg::HudMirituhg,
     pause_menu::pause_menu,
     ui_data::UIData,
@@ -48,11 +35,11 @@ use ui::{
 
 use crate::{
     game_data::{GameData, Graphics},
-    input_manager::Action,
-    map::map::Map,
+    map::map::load_maps,
 };
 
 mod entity;
+mod floor;
 mod fps_counter;
 mod game_data;
 mod game_state;
@@ -62,9 +49,12 @@ mod map;
 mod physics;
 mod rand_utils;
 mod room;
+mod save;
+mod schedule;
 mod settings;
 mod sprite;
 mod systems;
+mod time;
 mod timer;
 mod ui;
 
@@ -136,19 +126,10 @@ async fn main() {
         focus: None,
     };
 
-    let mut fullscreen = false;
-
     let mut fps_counter = FPSCounter::default();
 
     let hud_heart_texture = load_texture_bytes(include_bytes!("../assets/ui/heart_01.png"));
-    let hopper_texture = load_texture_bytes(include_bytes!("../assets/entities/hopper_01.png"));
-    let spitter_texture = load_texture_bytes(include_bytes!("../assets/entities/spitter.png"));
-    let stomper_texture = load_texture_bytes(include_bytes!("../assets/entities/stomper.png"));
-    let mirituhg_texture = load_texture_bytes(include_bytes!("../assets/entities/mirituhg.png"));
     let skull_texture = load_texture_bytes(include_bytes!("../assets/entities/skull_01.png"));
-    let bullet_texture = load_texture_bytes(include_bytes!("../assets/entities/bullet_01.png"));
-    let bullet_enemy_texture =
-        load_texture_bytes(include_bytes!("../assets/entities/bullet_enemy.png"));
     let dust_texture = load_texture_bytes(include_bytes!("../assets/entities/dust_01.png"));
     let blood_texture = load_texture_bytes(include_bytes!("../assets/entities/blood_01.png"));
     let aberration_meter_texture =
@@ -164,11 +145,6 @@ async fn main() {
         load_texture_bytes(include_bytes!("../assets/ui/end_game_screen.png"));
     let player_texture = load_texture_bytes(include_bytes!("../assets/entities/player_01.png"));
     let intro_screen_texture = load_texture_bytes(include_bytes!("../assets/ui/intro_screen.png"));
-    let health_texture = load_texture_bytes(include_bytes!("../assets/entities/health.png"));
-    let anomaly_big_texture =
-        load_texture_bytes(include_bytes!("../assets/entities/anomaly_big_01.png"));
-    let anomaly_small_texture =
-        load_texture_bytes(include_bytes!("../assets/entities/anomaly_small_01.png"));
     let upgrade_frame_inner_texture =
         load_texture_bytes(include_bytes!("../assets/ui/upgrade_frame_inner.png"));
     let upgrade_frame_inner_dark_texture =
@@ -215,23 +191,14 @@ async fn main() {
     let color_material = create_sprite_color_material();
     materials.insert("color".to_string(), GameMaterial::Color(color_material));
 
-    let textures = HashMap::from([
-        ("hopper", hopper_texture),
-        ("spitter", spitter_texture),
-        ("stomper", stomper_texture),
-        ("mirituhg", mirituhg_texture),
+    let mut textures = [
         ("skull", skull_texture),
-        ("bullet", bullet_texture),
-        ("bullet_enemy", bullet_enemy_texture),
         ("dust", dust_texture),
         ("blood", blood_texture),
         ("player", player_texture),
         ("intro_screen", intro_screen_texture),
         ("hud_heart", hud_heart_texture),
         ("aberration_meter", aberration_meter_texture),
-        ("health", health_texture),
-        ("anomaly_big", anomaly_big_texture),
-        ("anomaly_small", anomaly_small_texture),
         ("upgrade_frame_inner", upgrade_frame_inner_texture),
         ("upgrade_frame_inner_dark", upgrade_frame_inner_dark_texture),
         ("upgrade_banner_item", upgrade_banner_item_texture),
@@ -249,7 +216,11 @@ async fn main() {
         ),
         ("upgrade_move_speed", upgrade_move_speed_texture),
         ("upgrade_items", upgrade_items_texture),
-    ]);
+    ]
+    .into_iter()
+    .map(|(name, texture)| (name.to_owned(), texture))
+    .collect::<HashMap<_, _>>();
+    let prefabs = load_prefabs(&mut textures).await;
 
     let graphics = Graphics {
         aberration_meter_material: create_aberration_meter_material(),
@@ -333,43 +304,19 @@ async fn main() {
 
     let settings = GameSettings::default();
 
-    let mut entity_index = 0;
+    let mut ecs = Ecs::default();
 
     // Map
     let tileset = load_texture_bytes(include_bytes!("../assets/map/tileset_01.png"));
 
-    let tiled_map1_json = include_str!("../assets/map/example_01.tmj");
-    let tiled_map1 =
-        load_map(tiled_map1_json, &[("tileset_01.png", tileset.clone())], &[]).unwrap();
-    entity_index += 1;
-    let map1 = Map::new(Entity(entity_index), &settings, tiled_map1);
-
-    let tiled_map2_json = include_str!("../assets/map/map2.tmj");
-    let tiled_map2 =
-        load_map(tiled_map2_json, &[("tileset_01.png", tileset.clone())], &[]).unwrap();
-    entity_index += 1;
-    let map2 = Map::new(Entity(entity_index), &settings, tiled_map2);
-
-    let tiled_map3_json = include_str!("../assets/map/map3.tmj");
-    let tiled_map3 =
-        load_map(tiled_map3_json, &[("tileset_01.png", tileset.clone())], &[]).unwrap();
-    entity_index += 1;
-    let map3 = Map::new(Entity(entity_index), &settings, tiled_map3);
-
-    let tiled_map4_json = include_str!("../assets/map/map4.tmj");
-    let tiled_map4 =
-        load_map(tiled_map4_json, &[("tileset_01.png", tileset.clone())], &[]).unwrap();
-    entity_index += 1;
-    let map4 = Map::new(Entity(entity_index), &settings, tiled_map4);
-
-    let maps = vec![map4, map1, map2, map3];
+    let maps = load_maps(&mut ecs, &settings, &prefabs, &tileset).await;
 
     let mut data = GameData::new(
-        entity_index,
         settings,
         ui_data,
         maps,
         graphics,
+        prefabs,
         audio,
         death_texture,
         end_game_screen_texture,
@@ -377,15 +324,10 @@ async fn main() {
     data.reset();
     data.settings.set_window_size(WindowSize::W1440);
 
-    let mut ecs = Ecs::default();
-
-    let mut collisions = HashMap::new();
-    let mut damage_events = Vec::<DamageEvent>::new();
-    let mut death_events = Vec::<DeathEvent>::new();
-
     let hud_hearts = HudHearts::new(&data);
     let hud_mirituhg = HudMirituhg::new(overlay_mirituhg_texture, boss_health_bar_texture);
     let aberration_meter = AberrationMeter::new(&data);
+    let minimap = Minimap::new();
 
     data.graphics
         .aberration_meter_material
@@ -401,6 +343,115 @@ async fn main() {
 
     let mut upgrade_screen = UpgradeScreen::new(vec![]);
 
+    let mut schedule = Schedule::default();
+    add_game_systems(&mut schedule, &data.maps);
+    schedule
+        .add(
+            System::new("hud", Stage::UI, move |data, ecs| {
+                hud_hearts.draw(data, ecs);
+                aberration_meter.draw(data, ecs);
+                minimap.draw(data);
+                hud_mirituhg.draw(data, ecs);
+            })
+            .in_state(GameState::Playing),
+        )
+        .add(
+            System::new("dialogue", Stage::UI, |data, _| {
+                if !data.paused {
+                    data.dialogue.update(data.time.dt);
+                }
+                data.dialogue.draw(data);
+            })
+            .in_state(GameState::Playing)
+            .after("hud"),
+        )
+        .add(
+            System::new("intro_screen", Stage::UI, move |data, ecs| {
+                match intro_screen.update_and_draw(data) {
+                    Some(IntroChoice::Start) => {
+                        delete_save(data);
+                        data.reset();
+                        spawn_player(data, ecs);
+                        data.start_floor(ecs);
+                        data.state = GameState::Playing;
+                        // TODO: reset
+                    }
+                    Some(IntroChoice::Continue) => match load_save(&data.maps) {
+                        Some(save) => continue_run(data, ecs, save),
+                        None => delete_save(data),
+                    },
+                    None => {}
+                }
+            })
+            .in_state(GameState::Intro)
+            .after("hud"),
+        )
+        .add(
+            System::new("death_screen", Stage::UI, |data, _| {
+                data.death_screen.update(data.time.dt);
+                if data.death_screen.draw(data) {
+                    data.reset();
+                }
+            })
+            .run_if(|data| data.dead)
+            .after("intro_screen"),
+        )
+        .add(
+            System::new("end_game_screen", Stage::UI, |data, _| {
+                data.end_game_screen.update(data.time.dt);
+                if data.end_game_screen.draw(data) {
+                    data.state = GameState::Intro;
+                    data.game_completed = false;
+                }
+            })
+            .run_if(|data| data.game_completed)
+            .after("death_screen"),
+        )
+        .add(
+            System::new("fps_counter", Stage::UI, move |data, _| {
+                fps_counter.update_and_draw(data);
+            })
+            .run_if(|data| data.show_fps)
+            .after("end_game_screen"),
+        )
+        .add(
+            System::new("floor_map", Stage::UI, |data, _| floor_map(data))
+                .in_state(GameState::Playing)
+                .run_if(|data| data.paused && data.show_pause_menu)
+                .after("fps_counter")
+                .before("pause_menu"),
+        )
+        .add(
+            System::new("pause_menu", Stage::UI, |data, ecs| {
+                if pause_menu(data) {
+                    save_on_quit(data, ecs);
+                    data.quit_requested = true;
+                }
+            })
+            .run_if(|data| data.paused && data.show_pause_menu && !data.show_floor_map)
+            .after("fps_counter")
+            .before("upgrade_screen"),
+        )
+        .add(
+            System::new("upgrade_screen", Stage::UI, move |data, ecs| {
+                upgrade_screen.update(data, ecs);
+            })
+            .in_state(GameState::Playing)
+            .after("fps_counter"),
+        );
+
+    #[cfg(debug_assertions)]
+    {
+        let mut inspector = Inspector::default();
+        schedule.add(
+            System::new("inspector", Stage::UI, move |data, ecs| {
+                inspector.update_and_draw(data, ecs);
+            })
+            .in_state(GameState::Playing)
+            .after("upgrade_screen"),
+        );
+    }
+
     play_sound(
         &data.audio.music1,
         audio::PlaySoundParams {
@@ -425,39 +476,9 @@ async fn main() {
             data.camera.render_target = Some(camera_target);
         }
 
-        data.update();
+        data.update(get_frame_time());
         set_sound_volume(&data.audio.music1, data.settings.music_volume);
 
-        if data.state == GameState::Playing {
-            if is_key_pressed(KeyCode::F5) {
-                // Reset?
-                // reset_game(&mut data, &mut ecs);
-                spawn_mirituhg(&mut data, vec2(180., 120.), &mut ecs);
-            }
-
-            if is_key_pressed(KeyCode::F3) {
-                upgrade_screen.visible = true;
-                data.paused = true;
-            }
-
-            let despawned_entities = &ecs.marked_for_despawn.clone();
-            for entity in despawned_entities {
-                let entity_i = ecs.entities.iter().position(|e| e == entity);
-                if let Some(index) = entity_i {
-                    ecs.entities.remove(index);
-                    ecs.remove_all_components(entity);
-                }
-            }
-            // TODO: Hack
-            if despawned_entities.len() > 0 {
-                data.current_room.check_completed(&ecs);
-            }
-
-            if !data.paused {
-                ecs.marked_for_despawn.clear();
-                death_events.clear();
-            }
-        }
         data.graphics
             .aberration_meter_material
             .set_uniform("time", get_time() as f32);
@@ -467,235 +488,21 @@ async fn main() {
 
         set_mouse_cursor(miniquad::CursorIcon::Default);
 
-        data.input.update(&ecs, &data.camera);
         clear_background(Color::from_hex(0x060608));
 
-        if (is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt))
-            && is_key_pressed(KeyCode::Enter)
-        {
-            fullscreen = !fullscreen;
+        schedule.run(&mut data, &mut ecs, get_frame_time());
 
-            if fullscreen {
-                data.settings
-                    .set_window_size(settings::WindowSize::Fullscreen);
-            } else {
-                data.settings.set_window_size(WindowSize::default());
-            }
-        }
-
-        if is_key_pressed(KeyCode::F1) {
-            data.debug_collisions = !data.debug_collisions;
-        }
-
-        if data.input.is_just_pressed(Action::Pause) {
-            if data.paused {
-                data.paused = false;
-                data.show_pause_menu = false;
-            } else {
-                data.paused = true;
-                data.show_pause_menu = true;
-            }
-        }
-
-        if data.state == GameState::Playing {
-            if is_key_pressed(KeyCode::F6) {
-                data.next_room(&mut ecs);
-            }
-
-            // Map transition
-            if data.map_change_requested && data.screen_dimmer.just_dimmed {
-                if let Some(next_room) = data.next_room {
-                    data.map_change_requested = false;
-                    data.current_room.despawn(&mut ecs);
-                    data.next_room = None;
-                    data.current_room = next_room;
-                    let new_player_pos = data.spawn_map_entities(&mut ecs);
-                    data.current_room.started = true;
-                    let players = ecs.check_components(|e, comps| {
-                        comps.player_data.contains_key(e) && comps.positions.contains_key(e)
-                    });
-                    for player_e in &players {
-                        let pos = ecs.components.positions.get_mut(player_e).unwrap();
-                        *pos = new_player_pos;
-                    }
-                }
-            }
-            if data.pause_timer.just_completed() && !data.show_pause_menu && !upgrade_screen.visible
-            {
-                data.paused = false;
-            }
-
-            data.current_map().draw_base();
-
-            if !data.paused {
-                spawn_creatures(&mut data, &mut ecs);
-                update_timers(&mut ecs);
-                update_damageables(&mut ecs);
-                damage_on_collision(&ecs, &mut damage_events, &collisions);
-                despawn_on_collision(&mut data, &mut ecs, &collisions);
-                kill_entities(&data, &mut ecs, &mut death_events);
-                handle_death(&mut data, &mut ecs, &death_events);
-                update_player(&mut data, &mut ecs);
-                update_weapon(&mut ecs, &mut data);
-                update_enemies(&mut data, &mut ecs, &mut damage_events);
-                apply_damage(&mut data, &mut ecs, &mut damage_events);
-                update_animated_sprites(&mut ecs);
-                collisions = move_entities(&mut data, &mut ecs);
-
-                flash_on_damage(&mut ecs);
-            }
-            draw_animated_sprites(&mut ecs, &data);
-            data.current_map().draw_upper();
-
-            data.screen_dimmer.update();
-            let dim_progress = if data.screen_dimmer.dimming {
-                1. - data.screen_dimmer.progress()
-            } else {
-                data.screen_dimmer.progress()
-            };
-            draw_rectangle_ex(
-                0.,
-                0.,
-                360.,
-                240.,
-                DrawRectangleParams {
-                    color: Color::from_rgba(0, 0, 0, (dim_progress * 255.) as u8),
-                    ..Default::default()
-                },
-            );
-
-            if data.debug_collisions {
-                draw_colliders(&data, &ecs);
-                data.current_map().draw_colliders();
-            }
-
-            hud_hearts.draw(&data, &ecs);
-            aberration_meter.draw(&data, &ecs);
-            hud_mirituhg.draw(&data, &ecs);
-        }
-        if data.state == GameState::Intro {
-            if intro_screen.update_and_draw(&mut data) {
-                data.reset();
-                spawn_player(&mut data, &mut ecs);
-                data.next_room(&mut ecs);
-                data.state = GameState::Playing;
-                // TODO: reset
-            }
-        }
-        if data.dead {
-            data.death_screen.update();
-            if data.death_screen.draw(&data) {
-                data.reset();
-            }
-        }
-        if data.game_completed {
-            data.end_game_screen.update();
-            if data.end_game_screen.draw(&data) {
-                data.state = GameState::Intro;
-                data.game_completed = false;
-            }
-        }
-
-        if data.current_room.started {
-            if !data.current_room.upgrade_chosen {
-                upgrade_screen.visible = true;
-            }
-        }
-
-        if data.show_fps {
-            fps_counter.update_and_draw(&mut data);
-        }
-
-        if data.current_room.completed && !data.map_change_requested && !data.game_completed {
-            data.next_room(&mut ecs);
-        }
-
-        if data.paused {
-            if data.show_pause_menu {
-                if pause_menu(&mut data) {
-                    break;
-                }
-            } else if upgrade_screen.visible {
-                upgrade_screen.upgrades = data.current_room.available_upgrades.clone();
-                if let Some(upgrade) = upgrade_screen.draw(&mut data) {
-                    let players =
-                        ecs.check_components(|e, comps| comps.player_data.contains_key(e));
-                    let player_data = ecs.components.player_data.get_mut(&players[0]).unwrap();
-                    let health = ecs.components.health.get_mut(&players[0]).unwrap();
-
-                    match upgrade {
-                        Upgrade::Item(ref item) => match item {
-                            ItemUpgrade::Hp(hp) => {
-                                health.hp += *hp;
-                            }
-                            ItemUpgrade::AnomalySmall => {
-                                player_data.aberration = (player_data.aberration - 0.1).max(0.);
-                            }
-                            ItemUpgrade::AnomalyBig => {
-                                player_data.aberration = (player_data.aberration - 0.5).max(0.);
-                            }
-                        },
-                        Upgrade::CommonUpgrade(ref upgrade) => match upgrade {
-                            CommonUpgrade::MaxHp(hp) => {
-                                health.hp += *hp as f32;
-                                player_data.upgrades.push(upgrade.clone())
-                            }
-                            CommonUpgrade::MoveSpeed(_) => {
-                                player_data.upgrades.push(upgrade.clone())
-                            }
-                            CommonUpgrade::ItemDropChance(increase) => {
-                                data.item_drop_chance_increase += increase;
-                            }
-                        },
-                        Upgrade::Weapon(ref weapon) => match weapon {
-                            WeaponType::Launcher => {
-                                data.weapon = Weapon::Launcher(Launcher::new());
-                            }
-                            WeaponType::Balls => {
-                                data.weapon = Weapon::Balls(Balls::new());
-                            }
-                            WeaponType::Dash => {
-                                data.weapon = Weapon::Dash(Dash::new());
-                            }
-                        },
-                        Upgrade::WeaponUpgrade(ref upgrade) => match upgrade {
-                            WeaponUpgrade::Launcher(ref upgrade) => {
-                                if let Weapon::Launcher(ref mut launcher) = data.weapon {
-                                    launcher.upgrades.push(upgrade.clone());
-                                }
-                            }
-                            WeaponUpgrade::Balls(ref upgrade) => {
-                                if let Weapon::Balls(ref mut balls) = data.weapon {
-                                    balls.upgrades.push(upgrade.clone());
-                                }
-                            }
-                            WeaponUpgrade::Dash(ref upgrade) => {
-                                if let Weapon::Dash(ref mut dash) = data.weapon {
-                                    dash.upgrades.push(upgrade.clone());
-                                }
-                            }
-                        },
-                    }
-
-                    data.current_room.upgrade_chosen = true;
-
-                    upgrade_screen.visible = false;
-                    data.paused = false;
-                }
-            };
+        if data.quit_requested {
+            break;
         }
 
         if let Some(render_target) = &mut data.camera.render_target {
             post_processing_material.set_uniform("intensity", 0.21f32);
             post_processing_material.set_uniform("time", get_time() as f32);
             post_processing_material.set_uniform("texture_size", render_target.texture.size());
-            let players = ecs.check_components(|e, comps| comps.player_data.contains_key(e));
-            if players.len() > 0 {
-                if let Some(player_data) = ecs.components.player_data.get_mut(&players[0]) {
-                    post_processing_material
-                        .set_uniform("hue_shift", player_data.aberration * 0.01);
-                    post_processing_material.set_uniform("intensity", player_data.aberration + 0.2);
-                }
+            if let Some(player_data) = ecs.query_ref::<&PlayerData>().next() {
+                post_processing_material.set_uniform("hue_shift", player_data.aberration * 0.01);
+                post_processing_material.set_uniform("intensity", player_data.aberration + 0.2);
             }
 
             set_default_camera();
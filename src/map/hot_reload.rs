@@ -0,0 +1,52 @@
+use std::{collections::HashMap, fs, time::SystemTime};
+
+use crate::game_data::GameData;
+
+use super::map::Map;
+
+/// Reloads the map of the current room when its file changes, so rooms can
+/// be edited in Tiled while the game runs. Only the tiles and collisions
+/// change, the room's entities stay as they are.
+pub struct MapWatcher {
+    modified: HashMap<String, SystemTime>,
+}
+
+impl MapWatcher {
+    /// Notes the time of every map file, so maps edited before their room is
+    /// entered are reloaded too.
+    pub fn new(maps: &[Map]) -> Self {
+        let modified = maps
+            .iter()
+            .filter_map(|map| {
+                let path = map_path(&map.name);
+                let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+                modified.ok().map(|modified| (path, modified))
+            })
+            .collect();
+        Self { modified }
+    }
+
+    pub fn update(&mut self, data: &mut GameData) {
+        let index = data.current_room.map_index;
+        let path = map_path(&data.maps[index].name);
+        let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) else {
+            return;
+        };
+        match self.modified.insert(path.clone(), modified) {
+            Some(previous) if previous != modified => {}
+            _ => return,
+        }
+
+        let result = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| data.maps[index].reload(&data.settings, &data.prefabs, &json));
+        match result {
+            Ok(()) => data.navigation.clear(),
+            Err(e) => eprintln!("could not reload `{}`: {}", path, e),
+        }
+    }
+}
+
+fn map_path(name: &str) -> String {
+    format!("assets/map/{}.tmj", name)
+}
@@ -1,56 +1,215 @@
 use std::collections::{HashMap, HashSet};
 
 use macroquad::prelude::*;
-use macroquad_tiled::Map as TiledMap;
+use macroquad_tiled::{load_map, Map as TiledMap};
+use nanoserde::DeJson;
 
-use crate::{entity::entity_id::Entity, settings::GameSettings};
+use crate::{
+    entity::{entities::Ecs, entity_id::Entity, prefab::Prefab},
+    floor::Direction,
+    settings::GameSettings,
+};
+
+use super::{
+    meta::{load_map_meta, MapMeta},
+    spawn_point::SpawnPointDef,
+    tiled_json::{property, TiledJson},
+    trigger::{load_trigger_volumes, TriggerVolumeDef},
+};
+
+/// Effects of the tiles entities stand on, from the tileset's tile
+/// properties in Tiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileProperty {
+    Hurt,
+    Slow,
+    Anomaly,
+    Ice,
+}
+
+impl TileProperty {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hurt" => Some(TileProperty::Hurt),
+            "slow" => Some(TileProperty::Slow),
+            "anomaly" => Some(TileProperty::Anomaly),
+            "ice" => Some(TileProperty::Ice),
+            _ => None,
+        }
+    }
+}
 
 pub struct Map {
     pub id: Entity,
+    /// File name in `assets/map` without the extension.
+    pub name: String,
+    pub meta: MapMeta,
     pub tiled_map: TiledMap,
     pub tileset_collision_map: HashMap<String, HashSet<usize>>,
     pub map_collision: HashSet<(usize, usize)>,
+    /// Tiles of every layer that have the property.
+    pub tile_properties: HashMap<TileProperty, HashSet<(usize, usize)>>,
+    pub trigger_volumes: Vec<TriggerVolumeDef>,
+    /// Objects with a `door` property, by the direction they lead in.
+    pub doors: HashMap<Direction, Vec2>,
+    pub spawn_points: Vec<SpawnPointDef>,
+    tileset: Texture2D,
     map_rect: Rect,
 }
 
+/// Loads every map listed in `assets/map/maps.json`. Which rooms they are used
+/// for comes from their properties, the order doesn't matter.
+pub async fn load_maps(
+    ecs: &mut Ecs,
+    settings: &GameSettings,
+    prefabs: &HashMap<String, Prefab>,
+    tileset: &Texture2D,
+) -> Vec<Map> {
+    let index = load_string("map/maps.json").await.unwrap();
+    let names = Vec::<String>::deserialize_json(&index)
+        .unwrap_or_else(|e| panic!("invalid map list: {}", e));
+
+    let mut maps = vec![];
+    for name in names {
+        let json = load_string(&format!("map/{}.tmj", name)).await.unwrap();
+        let map = Map::load(ecs.spawn(), settings, prefabs, &name, &json, tileset)
+            .unwrap_or_else(|e| panic!("invalid map `{}`: {}", name, e));
+        maps.push(map);
+    }
+    maps
+}
+
+pub struct TileHit {
+    pub tile: (usize, usize),
+    pub point: Vec2,
+    /// Normal of the tile side the ray entered through, zero when the ray
+    /// starts inside the tile.
+    pub normal: Vec2,
+    /// Fraction of the way from `from` to `to`.
+    pub fraction: f32,
+}
+
 impl Map {
-    pub fn new(id: Entity, settings: &GameSettings, map: TiledMap) -> Self {
+    pub fn load(
+        id: Entity,
+        settings: &GameSettings,
+        prefabs: &HashMap<String, Prefab>,
+        name: &str,
+        json: &str,
+        tileset: &Texture2D,
+    ) -> Result<Self, String> {
+        let map = load_map(json, &[("tileset_01.png", tileset.clone())], &[])
+            .map_err(|e| format!("{:?}", e))?;
+        Map::new(id, settings, prefabs, name, map, json, tileset)
+    }
+
+    /// `json` is the source of `map`, for what macroquad-tiled doesn't read.
+    /// Spawn points may only name enemies of `prefabs`.
+    pub fn new(
+        id: Entity,
+        settings: &GameSettings,
+        prefabs: &HashMap<String, Prefab>,
+        name: &str,
+        map: TiledMap,
+        json: &str,
+        tileset: &Texture2D,
+    ) -> Result<Self, String> {
         let mut tileset_collision_map = HashMap::<String, HashSet<usize>>::new();
+        let mut tileset_property_map = HashMap::<String, HashMap<usize, Vec<TileProperty>>>::new();
         for tileset in &map.raw_tiled_map.tilesets {
             let mut collision = HashSet::<usize>::new();
+            let mut properties = HashMap::<usize, Vec<TileProperty>>::new();
             for tile in &tileset.tiles {
                 let has_collision = tile.properties.iter().any(|prop| prop.name == "collision");
                 if has_collision {
                     collision.insert(tile.id);
                 }
+                for prop in &tile.properties {
+                    if let Some(property) = TileProperty::from_name(&prop.name) {
+                        if prop.value != "false" {
+                            properties.entry(tile.id).or_default().push(property);
+                        }
+                    }
+                }
             }
             tileset_collision_map.insert(tileset.name.clone(), collision);
+            tileset_property_map.insert(tileset.name.clone(), properties);
         }
 
         let mut map_collision = HashSet::<(usize, usize)>::new();
+        let mut tile_properties = HashMap::<TileProperty, HashSet<(usize, usize)>>::new();
         for (_, layer) in &map.layers {
             let layer_width = layer.width;
             for (tile_index, tile) in layer.data.iter().enumerate() {
                 if let Some(tile) = tile {
+                    let tile_x = tile_index % layer_width as usize;
+                    let tile_y = tile_index / layer_width as usize;
                     let ts = tileset_collision_map.get(&tile.tileset);
                     if let Some(ts) = ts {
                         if ts.contains(&(tile.id as usize)) {
-                            let tile_x = tile_index % layer_width as usize;
-                            let tile_y = tile_index / layer_width as usize;
                             map_collision.insert((tile_x, tile_y));
                         }
                     }
+                    let properties = tileset_property_map
+                        .get(&tile.tileset)
+                        .and_then(|ts| ts.get(&(tile.id as usize)));
+                    for property in properties.into_iter().flatten() {
+                        tile_properties
+                            .entry(*property)
+                            .or_default()
+                            .insert((tile_x, tile_y));
+                    }
                 }
             }
         }
 
-        Self {
+        let json = TiledJson::parse(json)?;
+        let mut doors = HashMap::new();
+        let mut spawn_points = vec![];
+        for object in json.objects() {
+            let object_pos = vec2(object.x + 4., object.y - 4.);
+            if let Some(dir) = property(&object.properties, "door") {
+                let dir = Direction::from_name(dir)
+                    .ok_or_else(|| format!("invalid door direction `{}`", dir))?;
+                doors.insert(dir, object_pos);
+            }
+            if object.properties.iter().any(|prop| prop.name == "spawn") {
+                spawn_points.push(SpawnPointDef::parse(
+                    object_pos,
+                    &object.properties,
+                    prefabs,
+                )?);
+            }
+        }
+
+        Ok(Self {
             id,
+            name: name.to_string(),
+            meta: load_map_meta(&json)?,
             tiled_map: map,
             tileset_collision_map,
             map_collision,
+            tile_properties,
+            trigger_volumes: load_trigger_volumes(&json)
+                .map_err(|e| format!("invalid trigger volumes: {}", e))?,
+            doors,
+            spawn_points,
+            tileset: tileset.clone(),
             map_rect: Rect::new(0., 0., settings.resolution.x, settings.resolution.y),
-        }
+        })
+    }
+
+    /// Replaces the map with a new version of its file, keeping the old one
+    /// when the new one doesn't load.
+    #[cfg(debug_assertions)]
+    pub fn reload(
+        &mut self,
+        settings: &GameSettings,
+        prefabs: &HashMap<String, Prefab>,
+        json: &str,
+    ) -> Result<(), String> {
+        *self = Map::load(self.id, settings, prefabs, &self.name, json, &self.tileset)?;
+        Ok(())
     }
 
     pub fn draw_base(&self) {
@@ -67,5 +226,158 @@ impl Map {
         for (x, y) in &self.map_collision {
             draw_rectangle_lines(*x as f32 * 8., *y as f32 * 8., 8., 8., 1., GREEN);
         }
+        for (property, tiles) in &self.tile_properties {
+            let color = match property {
+                TileProperty::Hurt => RED,
+                TileProperty::Slow => BROWN,
+                TileProperty::Anomaly => PURPLE,
+                TileProperty::Ice => SKYBLUE,
+            };
+            for (x, y) in tiles {
+                draw_rectangle_lines(*x as f32 * 8. + 1., *y as f32 * 8. + 1., 6., 6., 1., color);
+            }
+        }
+    }
+
+    /// First collision tile on the segment from `from` to `to`.
+    pub fn raycast(&self, from: Vec2, to: Vec2) -> Option<TileHit> {
+        raycast_tiles(&self.map_collision, from, to)
+    }
+
+    /// Whether the tile under the position has the property.
+    pub fn has_property(&self, pos: Vec2, property: TileProperty) -> bool {
+        let cell = (pos / 8.).floor();
+        cell.x >= 0.
+            && cell.y >= 0.
+            && self.tile_properties.get(&property).map_or(false, |tiles| {
+                tiles.contains(&(cell.x as usize, cell.y as usize))
+            })
+    }
+
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        self.raycast(from, to).is_none()
+    }
+}
+
+/// First of the tiles on the segment from `from` to `to`. Walks the 8px grid
+/// cell by cell (DDA), so only the tiles the segment passes are checked.
+fn raycast_tiles(tiles: &HashSet<(usize, usize)>, from: Vec2, to: Vec2) -> Option<TileHit> {
+    let motion = to - from;
+    let mut cell = (from / 8.).floor();
+    let end_cell = (to / 8.).floor();
+    let step = motion.signum();
+    let delta = (8. / motion.abs()).min(Vec2::splat(f32::MAX));
+    let next_boundary = vec2(
+        if motion.x > 0. { cell.x + 1. } else { cell.x },
+        if motion.y > 0. { cell.y + 1. } else { cell.y },
+    ) * 8.;
+    let mut t_max = vec2(
+        if motion.x != 0. {
+            (next_boundary.x - from.x) / motion.x
+        } else {
+            f32::MAX
+        },
+        if motion.y != 0. {
+            (next_boundary.y - from.y) / motion.y
+        } else {
+            f32::MAX
+        },
+    );
+    let mut fraction = 0.;
+    let mut normal = Vec2::ZERO;
+
+    loop {
+        if cell.x >= 0. && cell.y >= 0. && tiles.contains(&(cell.x as usize, cell.y as usize)) {
+            return Some(TileHit {
+                tile: (cell.x as usize, cell.y as usize),
+                point: from + motion * fraction,
+                normal,
+                fraction,
+            });
+        }
+        if cell == end_cell {
+            return None;
+        }
+        if t_max.x < t_max.y {
+            fraction = t_max.x;
+            cell.x += step.x;
+            t_max.x += delta.x;
+            normal = vec2(-step.x, 0.);
+        } else {
+            fraction = t_max.y;
+            cell.y += step.y;
+            t_max.y += delta.y;
+            normal = vec2(0., -step.y);
+        }
+        if fraction > 1. {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_hit(hit: Option<TileHit>, tile: (usize, usize), fraction: f32, normal: Vec2) {
+        let hit = hit.expect("no hit");
+        assert_eq!(hit.tile, tile);
+        assert!((hit.fraction - fraction).abs() < 1e-5, "{}", hit.fraction);
+        assert_eq!(hit.normal, normal);
+    }
+
+    #[test]
+    fn zero_length_rays_only_hit_the_tile_they_are_in() {
+        let tiles = HashSet::from([(1, 0)]);
+
+        assert!(raycast_tiles(&tiles, vec2(4., 4.), vec2(4., 4.)).is_none());
+        assert_hit(
+            raycast_tiles(&tiles, vec2(12., 4.), vec2(12., 4.)),
+            (1, 0),
+            0.,
+            Vec2::ZERO,
+        );
+    }
+
+    #[test]
+    fn rays_starting_inside_a_tile_hit_it_at_once() {
+        let tiles = HashSet::from([(1, 0), (3, 0)]);
+
+        let hit = raycast_tiles(&tiles, vec2(12., 4.), vec2(36., 4.));
+        assert_hit(hit, (1, 0), 0., Vec2::ZERO);
+    }
+
+    #[test]
+    fn casts_along_the_x_axis() {
+        let tiles = HashSet::from([(3, 0)]);
+
+        let hit = raycast_tiles(&tiles, vec2(4., 4.), vec2(36., 4.));
+        assert_hit(hit, (3, 0), 20. / 32., vec2(-1., 0.));
+        let hit = raycast_tiles(&tiles, vec2(60., 4.), vec2(4., 4.));
+        assert_hit(hit, (3, 0), 28. / 56., vec2(1., 0.));
+
+        // Stops short of the tile
+        assert!(raycast_tiles(&tiles, vec2(4., 4.), vec2(20., 4.)).is_none());
+    }
+
+    #[test]
+    fn casts_along_the_y_axis() {
+        let tiles = HashSet::from([(0, 1)]);
+
+        let hit = raycast_tiles(&tiles, vec2(4., 36.), vec2(4., 4.));
+        assert_hit(hit, (0, 1), 20. / 32., vec2(0., 1.));
+        let hit = raycast_tiles(&tiles, vec2(4., 2.), vec2(4., 30.));
+        assert_hit(hit, (0, 1), 6. / 28., vec2(0., -1.));
+    }
+
+    #[test]
+    fn casts_across_both_axes() {
+        let tiles = HashSet::from([(2, 1)]);
+
+        // Crosses x = 16 at y = 12, inside the tile's row
+        let hit = raycast_tiles(&tiles, vec2(4., 8.), vec2(28., 16.));
+        assert_hit(hit, (2, 1), 0.5, vec2(-1., 0.));
+        // Passes above the tile
+        assert!(raycast_tiles(&tiles, vec2(4., 0.), vec2(28., 6.)).is_none());
     }
 }
@@ -0,0 +1,48 @@
+use crate::floor::RoomKind;
+
+use super::tiled_json::{property, TiledJson};
+
+/// What the floor generator needs to know about a map, from the custom
+/// properties of the map in Tiled.
+pub struct MapMeta {
+    /// `room_types`, a comma separated list. Combat rooms when missing.
+    pub room_kinds: Vec<RoomKind>,
+    /// `weight`, how likely the map is picked among the ones that fit.
+    pub weight: f32,
+    /// `min_depth`, the number of doors between the start room and the
+    /// first room that may use the map.
+    pub min_depth: usize,
+}
+
+pub fn load_map_meta(map: &TiledJson) -> Result<MapMeta, String> {
+    let property = |name: &str| property(&map.properties, name);
+
+    let room_kinds = match property("room_types") {
+        Some(kinds) => kinds
+            .split(',')
+            .map(|kind| {
+                RoomKind::from_name(kind.trim())
+                    .ok_or_else(|| format!("unknown room type `{}`", kind.trim()))
+            })
+            .collect::<Result<_, _>>()?,
+        None => vec![RoomKind::Combat],
+    };
+    let weight = match property("weight") {
+        Some(weight) => weight
+            .parse()
+            .map_err(|_| format!("invalid weight `{}`", weight))?,
+        None => 1.,
+    };
+    let min_depth = match property("min_depth") {
+        Some(depth) => depth
+            .parse()
+            .map_err(|_| format!("invalid min depth `{}`", depth))?,
+        None => 0,
+    };
+
+    Ok(MapMeta {
+        room_kinds,
+        weight,
+        min_depth,
+    })
+}
@@ -1 +1,8 @@
+#[cfg(debug_assertions)]
+pub mod hot_reload;
 pub mod map;
+pub mod meta;
+pub mod nav;
+pub mod spawn_point;
+pub mod tiled_json;
+pub mod trigger;
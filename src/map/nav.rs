@@ -0,0 +1,353 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    f32::consts::SQRT_2,
+};
+
+use macroquad::prelude::*;
+
+use super::map::Map;
+
+/// Walls may overlap an agent standing in the center of a cell by this much,
+/// the collision resolution slides it around corners.
+const CORNER_SLACK: f32 = 2.;
+/// Distance between the points checked along a line.
+const LINE_STEP: f32 = 2.;
+/// The path cache starts over once it holds this many paths.
+const MAX_CACHED_PATHS: usize = 256;
+
+type Cell = (i32, i32);
+
+/// Walkable cells of the map's 8px grid for agents of one radius. Cells are
+/// blocked when an agent in their center would overlap a collision tile.
+pub struct NavGrid {
+    width: i32,
+    height: i32,
+    blocked: Vec<bool>,
+}
+
+impl NavGrid {
+    pub fn new(map: &Map, radius: f32) -> Self {
+        let width = map.tiled_map.raw_tiled_map.width as i32;
+        let height = map.tiled_map.raw_tiled_map.height as i32;
+        let clearance = (radius - CORNER_SLACK).max(0.);
+        let reach = (clearance / 8.).ceil() as i32;
+
+        let mut blocked = vec![false; (width * height) as usize];
+        for &(x, y) in &map.map_collision {
+            let tile = Rect::new(x as f32 * 8., y as f32 * 8., 8., 8.);
+            for cell_y in y as i32 - reach..=y as i32 + reach {
+                for cell_x in x as i32 - reach..=x as i32 + reach {
+                    if cell_x < 0 || cell_y < 0 || cell_x >= width || cell_y >= height {
+                        continue;
+                    }
+                    let center = cell_center((cell_x, cell_y));
+                    let closest = center.clamp(tile.point(), tile.point() + tile.size());
+                    if cell_x == x as i32 && cell_y == y as i32
+                        || center.distance(closest) < clearance
+                    {
+                        blocked[(cell_y * width + cell_x) as usize] = true;
+                    }
+                }
+            }
+        }
+
+        Self {
+            width,
+            height,
+            blocked,
+        }
+    }
+
+    pub fn is_walkable(&self, cell: Cell) -> bool {
+        let (x, y) = cell;
+        x >= 0
+            && y >= 0
+            && x < self.width
+            && y < self.height
+            && !self.blocked[(y * self.width + x) as usize]
+    }
+
+    /// Whether an agent can walk straight from `from` to `to`. The cell the
+    /// agent starts in is not checked, so it can leave a wall it was pushed
+    /// against.
+    pub fn walkable_line(&self, from: Vec2, to: Vec2) -> bool {
+        let start = cell_of(from);
+        let steps = (from.distance(to) / LINE_STEP).ceil() as usize;
+        (1..=steps).all(|i| {
+            let cell = cell_of(from.lerp(to, i as f32 / steps as f32));
+            cell == start || self.is_walkable(cell)
+        })
+    }
+
+    /// The neighbours an agent can step to from the cell, diagonals only when
+    /// they don't cut a corner.
+    fn neighbours(&self, cell: Cell, walkable: impl Fn(Cell) -> bool) -> Vec<(Cell, f32)> {
+        NEIGHBOURS
+            .iter()
+            .filter_map(|(dx, dy)| {
+                let next = (cell.0 + dx, cell.1 + dy);
+                let diagonal = *dx != 0 && *dy != 0;
+                if !walkable(next)
+                    || diagonal
+                        && !(walkable((cell.0 + dx, cell.1)) && walkable((cell.0, cell.1 + dy)))
+                {
+                    return None;
+                }
+                Some((next, if diagonal { SQRT_2 } else { 1. }))
+            })
+            .collect()
+    }
+
+    /// A* over the 8 neighbours of every cell, without cutting corners. The
+    /// path starts at the first point after `from` and ends in the cell of
+    /// `to`, straight stretches are merged into one point.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = cell_of(from);
+        let goal = cell_of(to);
+        // The goal may stand closer to a wall than the agent fits
+        let walkable = |cell: Cell| cell == goal || self.is_walkable(cell);
+
+        let mut open = BinaryHeap::new();
+        let mut came_from = HashMap::<Cell, Cell>::new();
+        let mut costs = HashMap::<Cell, f32>::new();
+        open.push(Node {
+            cell: start,
+            estimate: octile_distance(start, goal),
+        });
+        costs.insert(start, 0.);
+
+        while let Some(Node { cell, .. }) = open.pop() {
+            if cell == goal {
+                let mut cells = vec![cell];
+                let mut current = cell;
+                while let Some(previous) = came_from.get(&current) {
+                    cells.push(*previous);
+                    current = *previous;
+                }
+                cells.reverse();
+                return Some(self.smooth(&cells));
+            }
+
+            let cost = costs[&cell];
+            for (next, step_cost) in self.neighbours(cell, walkable) {
+                let next_cost = cost + step_cost;
+                if costs.get(&next).map_or(true, |c| next_cost < *c) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    open.push(Node {
+                        cell: next,
+                        estimate: next_cost + octile_distance(next, goal),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Skips every cell that can be reached in a straight line from an
+    /// earlier point of the path.
+    fn smooth(&self, cells: &[Cell]) -> Vec<Vec2> {
+        let points = cells
+            .iter()
+            .map(|cell| cell_center(*cell))
+            .collect::<Vec<_>>();
+        let mut path = vec![];
+        let mut current = 0;
+        while current + 1 < points.len() {
+            let next = (current + 1..points.len())
+                .rev()
+                .find(|i| self.walkable_line(points[current], points[*i]))
+                .unwrap_or(current + 1);
+            path.push(points[next]);
+            current = next;
+        }
+        path
+    }
+}
+
+const NEIGHBOURS: [Cell; 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+struct Node {
+    cell: Cell,
+    estimate: f32,
+}
+
+// Reversed, so the heap pops the lowest estimate first
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Node {}
+
+fn cell_of(pos: Vec2) -> Cell {
+    let cell = (pos / 8.).floor();
+    (cell.x as i32, cell.y as i32)
+}
+
+fn cell_center((x, y): Cell) -> Vec2 {
+    vec2(x as f32 * 8. + 4., y as f32 * 8. + 4.)
+}
+
+fn octile_distance(a: Cell, b: Cell) -> f32 {
+    let dx = (a.0 - b.0).abs() as f32;
+    let dy = (a.1 - b.1).abs() as f32;
+    dx.max(dy) + (SQRT_2 - 1.) * dx.min(dy)
+}
+
+/// Cost to reach one goal cell from every cell of a grid, so any number of
+/// agents can head for the goal for the price of a single search.
+pub struct FlowField {
+    goal: Cell,
+    costs: HashMap<Cell, f32>,
+}
+
+impl FlowField {
+    /// Dijkstra outwards from the goal over the walkable cells.
+    pub fn new(grid: &NavGrid, goal: Cell) -> Self {
+        let mut open = BinaryHeap::new();
+        let mut costs = HashMap::<Cell, f32>::new();
+        open.push(Node {
+            cell: goal,
+            estimate: 0.,
+        });
+        costs.insert(goal, 0.);
+
+        while let Some(Node { cell, estimate }) = open.pop() {
+            if estimate > costs[&cell] {
+                continue;
+            }
+            for (next, step_cost) in grid.neighbours(cell, |cell| grid.is_walkable(cell)) {
+                let next_cost = estimate + step_cost;
+                if costs.get(&next).map_or(true, |c| next_cost < *c) {
+                    costs.insert(next, next_cost);
+                    open.push(Node {
+                        cell: next,
+                        estimate: next_cost,
+                    });
+                }
+            }
+        }
+
+        Self { goal, costs }
+    }
+
+    /// Towards the cheapest neighbour of the cell under `pos`, `None` if the
+    /// goal can't be reached from there.
+    pub fn direction(&self, grid: &NavGrid, pos: Vec2) -> Option<Vec2> {
+        let cell = cell_of(pos);
+        if cell == self.goal {
+            return None;
+        }
+        // Agents pushed into a blocked cell step back to any reachable one
+        let (next, _) = grid
+            .neighbours(cell, |cell| self.costs.contains_key(&cell))
+            .into_iter()
+            .filter_map(|(next, _)| Some((next, self.costs.get(&next)?)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        Some((cell_center(next) - pos).normalize_or_zero())
+    }
+}
+
+/// Navigation grids and paths of the current room, cleared when the room
+/// changes. Grids are kept per agent radius, paths per start and goal cell.
+#[derive(Default)]
+pub struct Navigation {
+    grids: HashMap<u32, NavGrid>,
+    paths: HashMap<(u32, Cell, Cell), Option<Vec<Vec2>>>,
+    flow_fields: HashMap<u32, FlowField>,
+}
+
+impl Navigation {
+    pub fn clear(&mut self) {
+        self.grids.clear();
+        self.paths.clear();
+        self.flow_fields.clear();
+    }
+
+    pub fn grid(&mut self, map: &Map, radius: f32) -> &NavGrid {
+        self.grids
+            .entry(radius.ceil() as u32)
+            .or_insert_with(|| NavGrid::new(map, radius.ceil()))
+    }
+
+    pub fn find_path(&mut self, map: &Map, from: Vec2, to: Vec2, radius: f32) -> Option<&[Vec2]> {
+        let key = (radius.ceil() as u32, cell_of(from), cell_of(to));
+        if !self.paths.contains_key(&key) {
+            if self.paths.len() >= MAX_CACHED_PATHS {
+                self.paths.clear();
+            }
+            let path = self.grid(map, radius).find_path(from, to);
+            self.paths.insert(key, path);
+        }
+        self.paths[&key].as_deref()
+    }
+
+    /// Direction an agent should move in to get to `to`. Straight at it when
+    /// nothing is in the way, otherwise towards the furthest point of the path
+    /// it can walk to directly. Straight at it again when there is no way.
+    pub fn direction(&mut self, map: &Map, from: Vec2, to: Vec2, radius: f32) -> Vec2 {
+        if self.grid(map, radius).walkable_line(from, to) {
+            return (to - from).normalize_or_zero();
+        }
+
+        let Some(path) = self.find_path(map, from, to, radius) else {
+            return (to - from).normalize_or_zero();
+        };
+        let path = path.to_vec();
+        let grid = self.grid(map, radius);
+        let target = path
+            .iter()
+            .take_while(|point| grid.walkable_line(from, **point))
+            .last()
+            .or(path.first())
+            .copied()
+            .unwrap_or(to);
+        (target - from).normalize_or_zero()
+    }
+
+    /// Same as `direction` for crowds heading to the same goal. Instead of a
+    /// path for every agent they share a flow field, which is only searched
+    /// again when the goal moves to another cell, at most once per step.
+    pub fn flow_direction(&mut self, map: &Map, from: Vec2, to: Vec2, radius: f32) -> Vec2 {
+        let straight = (to - from).normalize_or_zero();
+        if self.grid(map, radius).walkable_line(from, to) {
+            return straight;
+        }
+
+        let key = radius.ceil() as u32;
+        let goal = cell_of(to);
+        let grid = &self.grids[&key];
+        let field = self
+            .flow_fields
+            .entry(key)
+            .or_insert_with(|| FlowField::new(grid, goal));
+        if field.goal != goal {
+            *field = FlowField::new(grid, goal);
+        }
+        field.direction(grid, from).unwrap_or(straight)
+    }
+}
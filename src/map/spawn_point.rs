@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+use crate::entity::prefab::Prefab;
+
+use super::tiled_json::{property, TiledPropertyJson};
+
+/// An object of the map with a `spawn` property. Without a `count` it takes
+/// enemies from the room's difficulty budget, with one it spawns that many
+/// of its own, for encounters made by hand.
+pub struct SpawnPointDef {
+    pub position: Vec2,
+    /// `enemies`, a comma separated list of the enemy prefabs it may spawn.
+    /// Any enemy with a spawn cost when empty.
+    pub enemies: Vec<String>,
+    /// `wave`, counting from 1.
+    pub wave: usize,
+    /// `delay`, seconds after the start of the wave.
+    pub delay: f32,
+    /// `count`
+    pub count: Option<usize>,
+}
+
+impl SpawnPointDef {
+    pub fn parse(
+        position: Vec2,
+        properties: &[TiledPropertyJson],
+        prefabs: &HashMap<String, Prefab>,
+    ) -> Result<Self, String> {
+        let property = |name: &str| property(properties, name);
+
+        let enemies = match property("enemies") {
+            Some(enemies) => enemies
+                .split(',')
+                .map(|enemy| match prefabs.get(enemy.trim()) {
+                    Some(prefab) if prefab.enemy => Ok(enemy.trim().to_string()),
+                    _ => Err(format!("unknown enemy `{}`", enemy.trim())),
+                })
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        let wave = match property("wave") {
+            Some(wave) => wave
+                .parse()
+                .ok()
+                .filter(|wave| *wave > 0)
+                .ok_or_else(|| format!("invalid wave `{}`", wave))?,
+            None => 1,
+        };
+        let delay = match property("delay") {
+            Some(delay) => delay
+                .parse()
+                .map_err(|_| format!("invalid spawn delay `{}`", delay))?,
+            None => 0.,
+        };
+        let count = match property("count") {
+            Some(count) => Some(
+                count
+                    .parse()
+                    .map_err(|_| format!("invalid spawn count `{}`", count))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            position,
+            enemies,
+            wave,
+            delay,
+            count,
+        })
+    }
+
+    pub fn allows(&self, enemy: &str) -> bool {
+        self.enemies.is_empty() || self.enemies.iter().any(|name| name == enemy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nanoserde::DeJson;
+
+    use super::*;
+
+    fn prefabs() -> HashMap<String, Prefab> {
+        HashMap::from([
+            (
+                "hopper".to_string(),
+                Prefab::deserialize_json(r#"{"enemy": true}"#).unwrap(),
+            ),
+            (
+                "heart".to_string(),
+                Prefab::deserialize_json(r#"{}"#).unwrap(),
+            ),
+        ])
+    }
+
+    fn properties(properties: &[(&str, &str)]) -> Vec<TiledPropertyJson> {
+        properties
+            .iter()
+            .map(|(name, value)| TiledPropertyJson {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_values_fall_back_to_the_defaults() {
+        let properties = properties(&[
+            ("spawn", ""),
+            ("enemies", ""),
+            ("wave", " "),
+            ("delay", ""),
+            ("count", ""),
+        ]);
+        let def = SpawnPointDef::parse(vec2(4., 8.), &properties, &prefabs()).unwrap();
+        assert_eq!(def.position, vec2(4., 8.));
+        assert!(def.enemies.is_empty());
+        assert_eq!(def.wave, 1);
+        assert_eq!(def.delay, 0.);
+        assert_eq!(def.count, None);
+    }
+
+    #[test]
+    fn reads_the_values() {
+        let properties = properties(&[
+            ("spawn", ""),
+            ("enemies", "hopper, hopper"),
+            ("wave", "2"),
+            ("delay", "1.5"),
+            ("count", "3"),
+        ]);
+        let def = SpawnPointDef::parse(Vec2::ZERO, &properties, &prefabs()).unwrap();
+        assert_eq!(def.enemies, ["hopper", "hopper"]);
+        assert_eq!(def.wave, 2);
+        assert_eq!(def.delay, 1.5);
+        assert_eq!(def.count, Some(3));
+    }
+
+    #[test]
+    fn rejects_wave_zero() {
+        let properties = properties(&[("spawn", ""), ("wave", "0")]);
+        let result = SpawnPointDef::parse(Vec2::ZERO, &properties, &prefabs());
+        assert_eq!(result.err(), Some("invalid wave `0`".to_string()));
+    }
+
+    #[test]
+    fn rejects_unknown_enemies() {
+        for enemy in ["dragon", "heart"] {
+            let properties = properties(&[("spawn", ""), ("enemies", enemy)]);
+            let result = SpawnPointDef::parse(Vec2::ZERO, &properties, &prefabs());
+            assert_eq!(result.err(), Some(format!("unknown enemy `{}`", enemy)));
+        }
+    }
+}
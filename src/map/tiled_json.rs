@@ -0,0 +1,63 @@
+use nanoserde::DeJson;
+
+// macroquad-tiled only keeps the position and size of objects and none of the
+// map's own properties, so the parts of a `.tmj` map read besides it are
+// deserialized once into these.
+#[derive(DeJson)]
+pub struct TiledJson {
+    #[nserde(default)]
+    pub properties: Vec<TiledPropertyJson>,
+    pub layers: Vec<TiledLayerJson>,
+}
+
+#[derive(DeJson)]
+pub struct TiledLayerJson {
+    #[nserde(default)]
+    pub objects: Vec<TiledObjectJson>,
+}
+
+#[derive(DeJson)]
+pub struct TiledObjectJson {
+    pub x: f32,
+    pub y: f32,
+    #[nserde(default)]
+    pub width: f32,
+    #[nserde(default)]
+    pub height: f32,
+    pub gid: Option<u32>,
+    pub polygon: Option<Vec<TiledPointJson>>,
+    #[nserde(default)]
+    pub properties: Vec<TiledPropertyJson>,
+}
+
+#[derive(DeJson)]
+pub struct TiledPointJson {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Only string properties are supported, like everywhere else in the maps.
+#[derive(DeJson)]
+pub struct TiledPropertyJson {
+    pub name: String,
+    pub value: String,
+}
+
+impl TiledJson {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        TiledJson::deserialize_json(json).map_err(|e| e.to_string())
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &TiledObjectJson> {
+        self.layers.iter().flat_map(|layer| &layer.objects)
+    }
+}
+
+/// The trimmed value of a property, empty values count as missing.
+pub fn property<'a>(properties: &'a [TiledPropertyJson], name: &str) -> Option<&'a str> {
+    properties
+        .iter()
+        .find(|prop| prop.name == name)
+        .map(|prop| prop.value.trim())
+        .filter(|value| !value.is_empty())
+}
@@ -0,0 +1,141 @@
+use crate::systems::collision::Shape;
+use std::collections::HashSet;
+
+use macroquad::prelude::*;
+
+use super::tiled_json::{property, TiledJson};
+
+/// What a trigger volume does when the player enters or leaves it. Written in
+/// Tiled as `name` or `name:argument`, several actions are separated by `;`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerAction {
+    /// `start_wave:<wave>` starts the spawn points of that wave, which then
+    /// don't start on their own.
+    StartWave(usize),
+    /// `play_sound:<name>`
+    PlaySound(String),
+    /// `lock_exits` keeps the room from being completed until unlocked.
+    LockExits,
+    UnlockExits,
+    /// `show_dialogue:<text>`
+    ShowDialogue(String),
+}
+
+impl TriggerAction {
+    pub fn parse(action: &str) -> Result<Self, String> {
+        let (name, argument) = match action.split_once(':') {
+            Some((name, argument)) => (name.trim(), Some(argument.trim())),
+            None => (action.trim(), None),
+        };
+        match (name, argument) {
+            ("start_wave", Some(wave)) => wave
+                .parse()
+                .ok()
+                .filter(|wave| *wave > 0)
+                .map(TriggerAction::StartWave)
+                .ok_or_else(|| format!("invalid wave `{}`", wave)),
+            ("play_sound", Some(sound)) => Ok(TriggerAction::PlaySound(sound.to_string())),
+            ("lock_exits", None) => Ok(TriggerAction::LockExits),
+            ("unlock_exits", None) => Ok(TriggerAction::UnlockExits),
+            ("show_dialogue", Some(text)) => Ok(TriggerAction::ShowDialogue(text.to_string())),
+            _ => Err(format!("unknown trigger action `{}`", action)),
+        }
+    }
+
+    pub fn parse_list(actions: &str) -> Result<Vec<Self>, String> {
+        actions
+            .split(';')
+            .filter(|action| !action.trim().is_empty())
+            .map(TriggerAction::parse)
+            .collect()
+    }
+}
+
+/// A rectangle or polygon object of the map with an `on_enter` or `on_exit`
+/// property. `once` removes the volume after the player entered it.
+pub struct TriggerVolumeDef {
+    pub position: Vec2,
+    pub shape: Shape,
+    pub on_enter: Vec<TriggerAction>,
+    pub on_exit: Vec<TriggerAction>,
+    pub once: bool,
+}
+
+/// Waves started by the `start_wave` actions of the volumes.
+pub fn triggered_waves(volumes: &[TriggerVolumeDef]) -> HashSet<usize> {
+    volumes
+        .iter()
+        .flat_map(|volume| volume.on_enter.iter().chain(&volume.on_exit))
+        .filter_map(|action| match action {
+            TriggerAction::StartWave(wave) => Some(*wave),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reads the trigger volumes from the object layers of a `.tmj` map.
+/// Polygons have to be convex, like the collider shapes.
+pub fn load_trigger_volumes(map: &TiledJson) -> Result<Vec<TriggerVolumeDef>, String> {
+    let mut volumes = vec![];
+    for object in map.objects() {
+        let property = |name: &str| property(&object.properties, name);
+        let on_enter = property("on_enter");
+        let on_exit = property("on_exit");
+        if object.gid.is_some() || (on_enter.is_none() && on_exit.is_none()) {
+            continue;
+        }
+
+        let (position, shape) = if let Some(polygon) = &object.polygon {
+            let points = polygon.iter().map(|p| vec2(p.x, p.y)).collect();
+            (vec2(object.x, object.y), Shape::Polygon { points })
+        } else if object.width > 0. && object.height > 0. {
+            let half_size = vec2(object.width, object.height) / 2.;
+            (
+                vec2(object.x, object.y) + half_size,
+                Shape::Aabb { half_size },
+            )
+        } else {
+            continue;
+        };
+
+        volumes.push(TriggerVolumeDef {
+            position,
+            shape,
+            on_enter: TriggerAction::parse_list(on_enter.unwrap_or_default())?,
+            on_exit: TriggerAction::parse_list(on_exit.unwrap_or_default())?,
+            once: property("once") == Some("true"),
+        });
+    }
+    Ok(volumes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_action_lists() {
+        let actions = TriggerAction::parse_list("start_wave:2; lock_exits;show_dialogue: Hi;");
+        assert_eq!(
+            actions,
+            Ok(vec![
+                TriggerAction::StartWave(2),
+                TriggerAction::LockExits,
+                TriggerAction::ShowDialogue("Hi".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_actions() {
+        for action in [
+            "start_wave:0",
+            "start_wave:1.5",
+            "start_wave",
+            "lock_exits:1",
+            "jump",
+        ] {
+            assert!(TriggerAction::parse(action).is_err(), "{}", action);
+        }
+    }
+}
@@ -3,17 +3,21 @@ use std::collections::HashMap;
 use macroquad::prelude::*;
 
 use crate::{
-    entity::entity_id::Entity,
-    game_data::GameData,
+    entity::{entities::Ecs, entity_id::Entity},
     map::map::Map,
-    systems::collision::{CircleCollider, ColliderType},
+    systems::collision::{Collider, ColliderType, CollisionResponse, Shape},
 };
 
+use super::spatial_hash::SpatialHash;
+
 #[derive(Debug, PartialEq)]
 pub struct Collision {
     pub point: Vec2,
     pub overlap: f32,
     pub normal: Vec2,
+    /// Fraction of the step's movement after which the contact starts. Checks
+    /// at the destination report 1.
+    pub time_of_impact: f32,
 }
 
 pub fn check_collision_circles(
@@ -33,42 +37,133 @@ pub fn check_collision_circles(
             point,
             normal,
             overlap,
+            time_of_impact: 1.,
         });
     }
 
     None
 }
 
-pub fn resolve_circle_collision(
-    source_entity: Entity,
+/// Narrow phase for any two shapes. Both are treated as a convex outline grown
+/// by a radius and tested on the axes that can separate them: the edge
+/// normals and the directions between the outline points. The normal points
+/// from the second shape to the first one.
+pub fn check_collision(
+    pos1: Vec2,
+    shape1: &Shape,
+    pos2: Vec2,
+    shape2: &Shape,
+) -> Option<Collision> {
+    if let (Shape::Circle { radius: radius1 }, Shape::Circle { radius: radius2 }) = (shape1, shape2)
+    {
+        return check_collision_circles(pos1, *radius1, pos2, *radius2);
+    }
+
+    let (core1, radius1) = shape1.core();
+    let (core2, radius2) = shape2.core();
+    let core1 = core1.iter().map(|p| pos1 + *p).collect::<Vec<_>>();
+    let core2 = core2.iter().map(|p| pos2 + *p).collect::<Vec<_>>();
+
+    let mut axes = vec![];
+    for core in [&core1, &core2] {
+        if core.len() < 2 {
+            continue;
+        }
+        for (i, a) in core.iter().enumerate() {
+            let edge = core[(i + 1) % core.len()] - *a;
+            if edge.length_squared() > f32::EPSILON {
+                axes.push(edge.perp().normalize());
+            }
+        }
+    }
+    for a in &core1 {
+        for b in &core2 {
+            let diff = *a - *b;
+            if diff.length_squared() > f32::EPSILON {
+                axes.push(diff.normalize());
+            }
+        }
+    }
+    if axes.is_empty() {
+        axes.push(Vec2::Y);
+    }
+
+    let mut best: Option<(f32, Vec2)> = None;
+    for axis in axes {
+        let (min1, max1) = project(&core1, axis);
+        let (min2, max2) = project(&core2, axis);
+        let (min1, max1) = (min1 - radius1, max1 + radius1);
+        let (min2, max2) = (min2 - radius2, max2 + radius2);
+
+        let push_forward = max2 - min1;
+        let push_back = max1 - min2;
+        let (penetration, normal) = if push_forward < push_back {
+            (push_forward, axis)
+        } else {
+            (push_back, -axis)
+        };
+        if penetration <= 0. {
+            return None;
+        }
+        if best.map_or(true, |(best_penetration, _)| penetration < best_penetration) {
+            best = Some((penetration, normal));
+        }
+    }
+
+    let (penetration, normal) = best?;
+    let support = core2
+        .iter()
+        .copied()
+        .max_by(|a, b| a.dot(normal).total_cmp(&b.dot(normal)))
+        .unwrap_or(pos2);
+    Some(Collision {
+        point: support + normal * radius2,
+        overlap: -penetration,
+        normal,
+        time_of_impact: 1.,
+    })
+}
+
+fn project(points: &[Vec2], axis: Vec2) -> (f32, f32) {
+    points
+        .iter()
+        .map(|p| p.dot(axis))
+        .fold((f32::MAX, f32::MIN), |(min, max), d| {
+            (min.min(d), max.max(d))
+        })
+}
+
+/// Pushes the collider at `index` out of the colliders before it in the list,
+/// so every pair is only resolved once.
+pub fn resolve_collision(
+    index: usize,
     pos: Vec2,
-    colliders: &Vec<(Entity, Vec2, &CircleCollider)>,
+    colliders: &[(Entity, Vec2, Collider)],
+    spatial_hash: &SpatialHash,
 ) -> (Vec2, HashMap<(Entity, Entity), Collision>) {
-    let collider = colliders
-        .iter()
-        .find(|(e, _, _)| source_entity == *e)
-        .unwrap();
+    let (source_entity, _, collider) = &colliders[index];
+    let radius = collider.shape.bounding_radius();
 
     let mut desired_pos = pos;
     let mut collisions = HashMap::new();
     for _ in 0..2 {
         let mut is_colliding = false;
-        for (coll_e, other_pos, other_coll) in colliders {
-            if *coll_e == source_entity {
+        for other_index in spatial_hash.query(desired_pos, radius) {
+            if other_index >= index {
                 break;
             }
-            if !collider.2.coll_type.should_collide(&other_coll.coll_type) {
+            let (coll_e, other_pos, other_coll) = &colliders[other_index];
+            if !collider.collides_with(other_coll) {
                 continue;
             }
-            if let Some(collision) = check_collision_circles(
-                desired_pos,
-                collider.2.radius,
-                *other_pos,
-                other_coll.radius,
-            ) {
-                is_colliding = true;
-                desired_pos = desired_pos - collision.normal * (collision.overlap + 0.01);
-                collisions.insert((source_entity, *coll_e), collision);
+            if let Some(collision) =
+                check_collision(desired_pos, &collider.shape, *other_pos, &other_coll.shape)
+            {
+                if collider.is_solid_with(other_coll) {
+                    is_colliding = true;
+                    desired_pos = desired_pos - collision.normal * (collision.overlap + 0.01);
+                }
+                collisions.insert((*source_entity, *coll_e), collision);
             }
         }
         if !is_colliding {
@@ -78,89 +173,482 @@ pub fn resolve_circle_collision(
     (desired_pos, collisions)
 }
 
-pub fn check_collision_aabb_circle(
-    data: &GameData,
-    rect: &Rect,
-    circle_pos: Vec2,
-    circle_radius: f32,
-) -> Option<Vec2> {
-    let center = rect.center();
-    let diff = circle_pos - center;
-    let clamped_distance = diff.clamp(
-        vec2(-rect.w / 2., -rect.h / 2.),
-        vec2(rect.w / 2., rect.h / 2.),
-    );
-    let closest_point = center + clamped_distance;
+const TILE_SHAPE: Shape = Shape::Aabb {
+    half_size: Vec2::splat(4.),
+};
 
-    if data.debug_collisions {
-        draw_circle_lines(circle_pos.x, circle_pos.y, circle_radius, 1., BLUE);
-        draw_circle(closest_point.x, closest_point.y, 1., GREEN);
+/// Pushes the collider out of the map tiles around it.
+pub fn resolve_map_collision(
+    source_entity: Entity,
+    map: &Map,
+    pos: Vec2,
+    collider: &Collider,
+) -> (Vec2, HashMap<(Entity, Entity), Collision>) {
+    let mut collisions = HashMap::new();
+    if !collider.collides_with_map() {
+        return (pos, collisions);
     }
+    let solid = collider.response == CollisionResponse::Solid;
+    let radius = collider.shape.bounding_radius();
 
-    let is_colliding =
-        (closest_point - circle_pos).length_squared() < circle_radius * circle_radius;
-    if data.debug_collisions && is_colliding {
-        draw_circle(closest_point.x, closest_point.y, 1., RED);
+    let mut desired_pos = pos;
+    for _ in 0..2 {
+        let mut is_colliding = false;
+        let min = ((desired_pos - radius) / 8.).floor().max(Vec2::ZERO);
+        let max = ((desired_pos + radius) / 8.).floor().max(Vec2::ZERO);
+        for x in min.x as usize..=max.x as usize {
+            for y in min.y as usize..=max.y as usize {
+                if !map.map_collision.contains(&(x, y)) {
+                    continue;
+                }
+                let tile_center = vec2(x as f32 * 8. + 4., y as f32 * 8. + 4.);
+                let Some(collision) =
+                    check_collision(desired_pos, &collider.shape, tile_center, &TILE_SHAPE)
+                else {
+                    continue;
+                };
+                if solid {
+                    is_colliding = true;
+                    desired_pos = desired_pos - collision.normal * (collision.overlap + 0.01);
+                }
+                collisions.insert((source_entity, map.id), collision);
+            }
+        }
+        if !is_colliding {
+            break;
+        }
     }
+    (desired_pos, collisions)
+}
+
+/// Earliest time (0 to 1) at which a circle moving by `motion` touches a
+/// static circle, with the normal pointing from the static circle to the
+/// moving one. Circles that already overlap hit at 0, unless they are moving
+/// apart.
+pub fn sweep_circle_circle(
+    start: Vec2,
+    motion: Vec2,
+    radius: f32,
+    other_pos: Vec2,
+    other_radius: f32,
+) -> Option<(f32, Vec2)> {
+    let combined_radii = radius + other_radius;
+    let diff = start - other_pos;
+    let b = diff.dot(motion);
+    let c = diff.length_squared() - combined_radii * combined_radii;
+
+    if c <= 0. {
+        return (b < 0.).then(|| (0., diff.normalize_or_zero()));
+    }
+
+    let a = motion.length_squared();
+    let discriminant = b * b - a * c;
+    if a < f32::EPSILON || discriminant < 0. {
+        return None;
+    }
+
+    let t = (-b - discriminant.sqrt()) / a;
+    if !(0. ..=1.).contains(&t) {
+        return None;
+    }
+    Some((t, (start + motion * t - other_pos).normalize_or_zero()))
+}
+
+/// Entry time of a point moving by `motion` into a rect, with the normal of
+/// the side it enters through.
+fn sweep_point_rect(start: Vec2, motion: Vec2, rect: &Rect) -> Option<(f32, Vec2)> {
+    let mut t_enter = 0f32;
+    let mut t_exit = 1f32;
+    let mut normal = Vec2::ZERO;
 
-    if is_colliding {
-        if data.debug_collisions {
-            draw_circle(closest_point.x, closest_point.y, 1., RED);
+    for (start, motion, min, max, axis) in [
+        (start.x, motion.x, rect.left(), rect.right(), Vec2::X),
+        (start.y, motion.y, rect.top(), rect.bottom(), Vec2::Y),
+    ] {
+        if motion.abs() < f32::EPSILON {
+            if start <= min || start >= max {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min - start) / motion;
+        let t2 = (max - start) / motion;
+        let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+        if near > t_enter {
+            t_enter = near;
+            normal = if motion > 0. { -axis } else { axis };
+        }
+        t_exit = t_exit.min(far);
+        if t_enter > t_exit {
+            return None;
         }
-        return Some(closest_point);
     }
 
-    None
+    // Already inside, the overlap is left to the regular resolution
+    if normal == Vec2::ZERO {
+        return None;
+    }
+    Some((t_enter, normal))
 }
 
-pub fn resolve_map_collision(
-    source_entity: Entity,
-    data: &GameData,
+/// Earliest time (0 to 1) at which a circle moving by `motion` touches a rect.
+/// The circle is swept as a point against the rect grown by the radius, which
+/// is two rects and a circle on every corner.
+pub fn sweep_circle_rect(
+    start: Vec2,
+    motion: Vec2,
+    radius: f32,
+    rect: &Rect,
+) -> Option<(f32, Vec2)> {
+    let wide = Rect::new(rect.x - radius, rect.y, rect.w + radius * 2., rect.h);
+    let tall = Rect::new(rect.x, rect.y - radius, rect.w, rect.h + radius * 2.);
+    let corners = [
+        rect.point(),
+        vec2(rect.right(), rect.top()),
+        vec2(rect.left(), rect.bottom()),
+        vec2(rect.right(), rect.bottom()),
+    ];
+
+    [
+        sweep_point_rect(start, motion, &wide),
+        sweep_point_rect(start, motion, &tall),
+    ]
+    .into_iter()
+    .chain(
+        corners
+            .into_iter()
+            .map(|corner| sweep_circle_circle(start, motion, radius, corner, 0.)),
+    )
+    .flatten()
+    .min_by(|(a, _), (b, _)| a.total_cmp(b))
+}
+
+/// Sweeps the collider at `index` from `start` to `end` against the map and
+/// every other collider, so fast movement can't tunnel through walls or
+/// other colliders, no matter which of them moves first. Overlap-only hits along the way are
+/// reported, at the first solid hit the collider stops and slides along it.
+/// Only circles are swept, the other shapes are too big and slow to tunnel.
+pub fn sweep_collider(
+    index: usize,
+    start: Vec2,
+    end: Vec2,
+    colliders: &[(Entity, Vec2, Collider)],
+    spatial_hash: &SpatialHash,
     map: &Map,
-    pos: Vec2,
-    collider: &CircleCollider,
 ) -> (Vec2, HashMap<(Entity, Entity), Collision>) {
-    if !collider.coll_type.should_collide(&ColliderType::Map) {
-        return (pos, HashMap::new());
-    }
-    let mut desired_pos = pos;
-    let tile_position = desired_pos / 8.;
-    let tile_position = (tile_position.x as i32, tile_position.y as i32);
-    let area_size = 1;
-    let area = (
-        (tile_position.0 - area_size)..=tile_position.0 + area_size,
-        (tile_position.1 - area_size)..=tile_position.1 + area_size,
-    );
+    let (source_entity, _, collider) = &colliders[index];
+    let source_entity = *source_entity;
+    let motion = end - start;
     let mut collisions = HashMap::new();
-    for _ in 0..2 {
-        let mut is_colliding = false;
-        for (x, y) in &map.map_collision {
-            if !(area.0.contains(&(*x as i32)) && area.1.contains(&(*y as i32))) {
-                continue;
-            }
-            let rect = Rect::new(*x as f32 * 8., *y as f32 * 8., 8., 8.);
-            if let Some(closest_point) =
-                check_collision_aabb_circle(&data, &rect, desired_pos, collider.radius)
-            {
-                is_colliding = true;
-                let diff_to_closest_point = closest_point - desired_pos;
-                let overlap = collider.radius - diff_to_closest_point.length();
-
-                collisions.insert(
-                    (source_entity, map.id),
-                    Collision {
-                        normal: Vec2::ZERO,
-                        overlap: 0.,
-                        point: closest_point,
-                    },
-                );
-
-                desired_pos = desired_pos - diff_to_closest_point.normalize() * (overlap + 0.01);
+    let Shape::Circle { radius } = collider.shape else {
+        return (end, collisions);
+    };
+    if motion.length_squared() < f32::EPSILON {
+        return (end, collisions);
+    }
+
+    let mut hits = vec![];
+    for other_index in spatial_hash.query_segment(start, end, radius) {
+        if other_index == index {
+            continue;
+        }
+        let (other_e, other_pos, other_coll) = &colliders[other_index];
+        if !collider.collides_with(other_coll) {
+            continue;
+        }
+        let hit = match &other_coll.shape {
+            Shape::Circle {
+                radius: other_radius,
+            } => sweep_circle_circle(start, motion, radius, *other_pos, *other_radius),
+            Shape::Aabb { half_size } => sweep_circle_rect(
+                start,
+                motion,
+                radius,
+                &Rect::new(
+                    other_pos.x - half_size.x,
+                    other_pos.y - half_size.y,
+                    half_size.x * 2.,
+                    half_size.y * 2.,
+                ),
+            ),
+            // Left to the regular resolution
+            Shape::Capsule { .. } | Shape::Polygon { .. } => None,
+        };
+        if let Some((t, normal)) = hit {
+            let point = start + motion * t - normal * radius;
+            hits.push((
+                *other_e,
+                t,
+                normal,
+                point,
+                collider.is_solid_with(other_coll),
+            ));
+        }
+    }
+
+    if collider.collides_with_map() {
+        let solid = collider.response == CollisionResponse::Solid;
+        let min = ((start.min(end) - radius) / 8.).floor();
+        let max = ((start.max(end) + radius) / 8.).floor();
+        let mut map_hit: Option<(f32, Vec2)> = None;
+        for x in min.x.max(0.) as usize..=max.x.max(0.) as usize {
+            for y in min.y.max(0.) as usize..=max.y.max(0.) as usize {
+                if !map.map_collision.contains(&(x, y)) {
+                    continue;
+                }
+                let rect = Rect::new(x as f32 * 8., y as f32 * 8., 8., 8.);
+                if let Some(hit) = sweep_circle_rect(start, motion, radius, &rect) {
+                    if map_hit.map_or(true, |(t, _)| hit.0 < t) {
+                        map_hit = Some(hit);
+                    }
+                }
             }
         }
-        if !is_colliding {
+        if let Some((t, normal)) = map_hit {
+            let point = start + motion * t - normal * radius;
+            hits.push((map.id, t, normal, point, solid));
+        }
+    }
+
+    hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut pos = end;
+    for (other_e, t, normal, point, solid) in hits {
+        collisions.insert(
+            (source_entity, other_e),
+            Collision {
+                point,
+                overlap: 0.,
+                normal,
+                time_of_impact: t,
+            },
+        );
+        if solid {
+            let remaining = motion * (1. - t);
+            pos = start + motion * t + normal * 0.01 + remaining - normal * remaining.dot(normal);
             break;
         }
     }
-    (desired_pos, collisions)
+    (pos, collisions)
+}
+
+pub struct RaycastHit {
+    /// The map's entity for tiles.
+    pub entity: Entity,
+    pub tile: Option<(usize, usize)>,
+    pub point: Vec2,
+    pub normal: Vec2,
+    /// Fraction of the way from `from` to `to`.
+    pub fraction: f32,
+}
+
+/// First hit on the segment from `from` to `to`, against the map tiles and
+/// the colliders on one of the `mask` layers. Capsules and polygons are
+/// tested with their bounding circle.
+pub fn raycast(ecs: &Ecs, map: &Map, from: Vec2, to: Vec2, mask: u32) -> Option<RaycastHit> {
+    let motion = to - from;
+    let mut closest = None;
+    if mask & ColliderType::Map.layer() != 0 {
+        closest = map.raycast(from, to).map(|hit| RaycastHit {
+            entity: map.id,
+            tile: Some(hit.tile),
+            point: hit.point,
+            normal: hit.normal,
+            fraction: hit.fraction,
+        });
+    }
+
+    for (entity, pos, collider) in ecs.query_ref::<(Entity, &Vec2, &Collider)>() {
+        if collider.layer & mask == 0 {
+            continue;
+        }
+        let hit = match &collider.shape {
+            Shape::Aabb { half_size } => sweep_point_rect(
+                from,
+                motion,
+                &Rect::new(
+                    pos.x - half_size.x,
+                    pos.y - half_size.y,
+                    half_size.x * 2.,
+                    half_size.y * 2.,
+                ),
+            ),
+            shape => sweep_circle_circle(from, motion, 0., *pos, shape.bounding_radius()),
+        };
+        let Some((fraction, normal)) = hit else {
+            continue;
+        };
+        if closest
+            .as_ref()
+            .map_or(true, |closest: &RaycastHit| fraction < closest.fraction)
+        {
+            closest = Some(RaycastHit {
+                entity,
+                tile: None,
+                point: from + motion * fraction,
+                normal,
+                fraction,
+            });
+        }
+    }
+    closest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    fn circle(radius: f32) -> Shape {
+        Shape::Circle { radius }
+    }
+
+    fn aabb(x: f32, y: f32) -> Shape {
+        Shape::Aabb {
+            half_size: vec2(x, y),
+        }
+    }
+
+    fn assert_contact(collision: Option<Collision>, overlap: f32, normal: Vec2) {
+        let collision = collision.expect("no collision");
+        assert_near(collision.overlap, overlap);
+        assert_near(collision.normal.x, normal.x);
+        assert_near(collision.normal.y, normal.y);
+    }
+
+    #[test]
+    fn collides_circles() {
+        let collision = check_collision(vec2(0., 0.), &circle(2.), vec2(4., 0.), &circle(3.));
+        assert_contact(collision, -1., vec2(-1., 0.));
+
+        assert!(check_collision(vec2(0., 0.), &circle(2.), vec2(6., 0.), &circle(3.)).is_none());
+    }
+
+    #[test]
+    fn collides_a_circle_with_an_aabb() {
+        let collision = check_collision(vec2(0., 0.), &circle(2.), vec2(3., 0.), &aabb(2., 2.));
+        assert_contact(collision, -1., vec2(-1., 0.));
+
+        // Near the corner the normal points away from it
+        let collision = check_collision(vec2(3., 3.), &circle(2.), vec2(0., 0.), &aabb(2., 2.));
+        let diagonal = Vec2::splat(std::f32::consts::FRAC_1_SQRT_2);
+        assert_contact(collision, 2f32.sqrt() - 2., diagonal);
+
+        // Inside the aabb's bounding box, but past the corner
+        assert!(
+            check_collision(vec2(3.6, 3.6), &circle(2.), vec2(0., 0.), &aabb(2., 2.)).is_none()
+        );
+    }
+
+    #[test]
+    fn collides_aabbs_along_the_smallest_overlap() {
+        let collision = check_collision(vec2(0., 0.), &aabb(2., 2.), vec2(3., 1.), &aabb(2., 2.));
+        assert_contact(collision, -1., vec2(-1., 0.));
+
+        assert!(
+            check_collision(vec2(0., 0.), &aabb(2., 2.), vec2(5., 0.), &aabb(2., 2.)).is_none()
+        );
+    }
+
+    #[test]
+    fn collides_a_capsule_with_a_circle() {
+        let capsule = Shape::Capsule {
+            a: vec2(-5., 0.),
+            b: vec2(5., 0.),
+            radius: 1.,
+        };
+
+        let collision = check_collision(vec2(0., 0.), &capsule, vec2(4., 1.5), &circle(1.));
+        assert_contact(collision, -0.5, vec2(0., -1.));
+
+        // Past the rounded end
+        assert!(check_collision(vec2(0., 0.), &capsule, vec2(7.5, 0.), &circle(1.)).is_none());
+        assert!(check_collision(vec2(0., 0.), &capsule, vec2(6.5, 1.5), &circle(1.)).is_none());
+    }
+
+    #[test]
+    fn collides_a_polygon_with_an_aabb() {
+        let triangle = Shape::Polygon {
+            points: vec![vec2(0., -2.), vec2(2., 2.), vec2(-2., 2.)],
+        };
+
+        let collision = check_collision(vec2(0., 0.), &triangle, vec2(0., 2.5), &aabb(1., 1.));
+        assert_contact(collision, -0.5, vec2(0., -1.));
+
+        assert!(check_collision(vec2(0., 0.), &triangle, vec2(0., 3.5), &aabb(1., 1.)).is_none());
+        // Beside the slanted edge
+        assert!(check_collision(vec2(0., 0.), &triangle, vec2(2.5, -1.), &aabb(1., 1.)).is_none());
+    }
+
+    #[test]
+    fn sweeps_a_circle_into_a_circle() {
+        let (t, normal) =
+            sweep_circle_circle(vec2(0., 0.), vec2(20., 0.), 1., vec2(10., 0.), 2.).unwrap();
+
+        assert_near(t, 7. / 20.);
+        assert_eq!(normal, vec2(-1., 0.));
+    }
+
+    #[test]
+    fn sweeps_past_or_short_of_a_circle() {
+        // Passes beside it
+        assert!(sweep_circle_circle(vec2(0., 5.), vec2(20., 0.), 1., vec2(10., 0.), 2.).is_none());
+        // Stops before it
+        assert!(sweep_circle_circle(vec2(0., 0.), vec2(5., 0.), 1., vec2(10., 0.), 2.).is_none());
+        // Moves away from it
+        assert!(sweep_circle_circle(vec2(0., 0.), vec2(-20., 0.), 1., vec2(10., 0.), 2.).is_none());
+        // Doesn't move
+        assert!(sweep_circle_circle(vec2(0., 0.), Vec2::ZERO, 1., vec2(10., 0.), 2.).is_none());
+    }
+
+    #[test]
+    fn overlapping_circles_hit_at_once_unless_moving_apart() {
+        let (t, normal) =
+            sweep_circle_circle(vec2(8., 0.), vec2(5., 0.), 1., vec2(10., 0.), 2.).unwrap();
+        assert_eq!(t, 0.);
+        assert_eq!(normal, vec2(-1., 0.));
+
+        assert!(sweep_circle_circle(vec2(8., 0.), vec2(-5., 0.), 1., vec2(10., 0.), 2.).is_none());
+    }
+
+    #[test]
+    fn sweeps_a_circle_into_the_sides_of_a_rect() {
+        let rect = Rect::new(10., 10., 10., 10.);
+
+        let (t, normal) = sweep_circle_rect(vec2(0., 15.), vec2(20., 0.), 2., &rect).unwrap();
+        assert_near(t, 8. / 20.);
+        assert_eq!(normal, vec2(-1., 0.));
+
+        let (t, normal) = sweep_circle_rect(vec2(15., 40.), vec2(0., -40.), 2., &rect).unwrap();
+        assert_near(t, 18. / 40.);
+        assert_eq!(normal, vec2(0., 1.));
+    }
+
+    #[test]
+    fn sweeps_a_circle_into_the_corner_of_a_rect() {
+        let rect = Rect::new(10., 10., 10., 10.);
+
+        // Diagonally at the top left corner, touching it after moving
+        // sqrt(2) * 10 - 2 along the diagonal
+        let (t, normal) = sweep_circle_rect(vec2(0., 0.), vec2(10., 10.), 2., &rect).unwrap();
+        assert_near(t, (200f32.sqrt() - 2.) / 200f32.sqrt());
+        assert_near(normal.x, -std::f32::consts::FRAC_1_SQRT_2);
+        assert_near(normal.y, -std::f32::consts::FRAC_1_SQRT_2);
+
+        // Cuts the corner of the grown rect, but passes 2.5 away from the
+        // rect's corner
+        let offset = 20. - 2.5 * std::f32::consts::SQRT_2;
+        assert!(sweep_circle_rect(vec2(0., offset), vec2(offset, -offset), 2., &rect).is_none());
+    }
+
+    #[test]
+    fn sweeps_short_of_or_past_a_rect() {
+        let rect = Rect::new(10., 10., 10., 10.);
+
+        assert!(sweep_circle_rect(vec2(0., 15.), vec2(5., 0.), 2., &rect).is_none());
+        assert!(sweep_circle_rect(vec2(0., 30.), vec2(30., 0.), 2., &rect).is_none());
+    }
 }
@@ -1 +1,2 @@
 pub mod collision;
+pub mod spatial_hash;
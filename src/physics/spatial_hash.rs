@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+use crate::{entity::entity_id::Entity, systems::collision::Collider};
+
+/// Same as the map tiles, most colliders only cover one or two cells.
+pub const CELL_SIZE: f32 = 8.;
+
+/// Uniform grid of the colliders moved in a physics step, so the narrow phase
+/// only has to look at colliders in the cells around a position. Stores
+/// indices into the collider list it was built from.
+#[derive(Default)]
+pub struct SpatialHash {
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new(colliders: &[(Entity, Vec2, Collider)]) -> Self {
+        let mut cells = HashMap::<(i32, i32), Vec<usize>>::new();
+        for (index, (_, pos, collider)) in colliders.iter().enumerate() {
+            for cell in cells_in_area(*pos, collider.shape.bounding_radius()) {
+                cells.entry(cell).or_default().push(index);
+            }
+        }
+        Self { cells }
+    }
+
+    /// Indices of the colliders sharing a cell with the circle, in ascending
+    /// order and without duplicates.
+    pub fn query(&self, pos: Vec2, radius: f32) -> Vec<usize> {
+        self.indices_in(cells_in_area(pos, radius))
+    }
+
+    /// Same as `query`, for the cells a circle moving from `start` to `end`
+    /// passes.
+    pub fn query_segment(&self, start: Vec2, end: Vec2, radius: f32) -> Vec<usize> {
+        self.indices_in(cells_in_rect(
+            start.min(end) - radius,
+            start.max(end) + radius,
+        ))
+    }
+
+    fn indices_in(&self, cells: impl Iterator<Item = (i32, i32)>) -> Vec<usize> {
+        let mut indices = cells
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Occupied cells with the amount of colliders in them.
+    pub fn occupancy(&self) -> impl Iterator<Item = ((i32, i32), usize)> + '_ {
+        self.cells
+            .iter()
+            .map(|(cell, indices)| (*cell, indices.len()))
+    }
+}
+
+fn cells_in_area(pos: Vec2, radius: f32) -> impl Iterator<Item = (i32, i32)> {
+    cells_in_rect(pos - radius, pos + radius)
+}
+
+fn cells_in_rect(min: Vec2, max: Vec2) -> impl Iterator<Item = (i32, i32)> {
+    let min = (min / CELL_SIZE).floor();
+    let max = (max / CELL_SIZE).floor();
+    (min.x as i32..=max.x as i32)
+        .flat_map(move |x| (min.y as i32..=max.y as i32).map(move |y| (x, y)))
+}
+
+pub fn draw_spatial_hash(spatial_hash: &SpatialHash) {
+    for ((x, y), count) in spatial_hash.occupancy() {
+        let color = match count {
+            1 => Color::from_rgba(0, 228, 48, 60),
+            2 => Color::from_rgba(253, 249, 0, 90),
+            _ => Color::from_rgba(230, 41, 55, 120),
+        };
+        draw_rectangle(
+            x as f32 * CELL_SIZE,
+            y as f32 * CELL_SIZE,
+            CELL_SIZE,
+            CELL_SIZE,
+            color,
+        );
+    }
+}
@@ -1,18 +1,21 @@
+use std::collections::HashMap;
+
 use macroquad::rand;
 
-use crate::entity::{
-    entities::Ecs,
-    upgrades::{Upgrade, Upgrades},
+use crate::{
+    entity::{
+        entities::Ecs,
+        entity_id::Entity,
+        pickup::Pickup,
+        prefab::{regular_enemies, Prefab},
+        spawner::Spawner,
+        tags::RoomEntity,
+        upgrades::{Upgrade, Upgrades},
+    },
+    floor::Direction,
+    map::spawn_point::SpawnPointDef,
 };
 
-#[derive(Debug, Clone, Copy)]
-pub enum Enemy {
-    Hopper,
-    Spitter,
-    Stomper,
-    Mirituhg,
-}
-
 #[derive(Clone, Copy)]
 pub enum Item {
     Health,
@@ -20,10 +23,27 @@ pub enum Item {
     AberrationRelief,
 }
 
+impl Item {
+    pub fn pickup(&self) -> Pickup {
+        match self {
+            Item::Health => Pickup::Health(1.),
+            Item::SuperHealth => Pickup::Health(3.),
+            Item::AberrationRelief => Pickup::AnomalyBig,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Room {
     pub map_index: usize,
-    pub enemies_to_spawn: Vec<Enemy>,
+    /// Index of the room on the floor.
+    pub floor_index: usize,
+    /// Door the player comes in through, `None` to start at the map's
+    /// player object.
+    pub entrance: Option<Direction>,
+    /// Prefab names of the enemies left in the difficulty budget, by the wave
+    /// of the spawn points that take them.
+    pub enemies_to_spawn: HashMap<usize, Vec<String>>,
     pub items_to_spawn: Vec<Item>,
     pub started: bool,
     pub aberration_completed: bool,
@@ -31,32 +51,27 @@ pub struct Room {
     pub available_upgrades: Vec<Upgrade>,
     pub upgrade_chosen: bool,
     pub entities_spawned: bool,
+    /// Set by trigger volumes, the room can't be completed while locked.
+    pub exits_locked: bool,
+    /// Spawn points of this and earlier waves are running, the next wave
+    /// starts once they are done and every enemy is dead.
+    pub wave: usize,
 }
 
 impl Room {
-    pub fn new(map_index: usize, difficulty: f32) -> Room {
-        let mut remaining_difficulty = difficulty;
-        let enemy_values = vec![
-            (Enemy::Hopper, 1.),
-            (Enemy::Spitter, 2.),
-            (Enemy::Stomper, 5.),
-        ];
-
-        let mut enemies = vec![];
-
-        while remaining_difficulty > 1. {
-            let rand_index = rand::gen_range(0, enemy_values.len());
-            let enemy = enemy_values[rand_index];
-            if enemy.1 > remaining_difficulty {
-                continue;
-            }
-            enemies.push(enemy.0);
-            remaining_difficulty -= enemy.1;
-        }
-
+    /// `spawn_points` are the ones of the map, the difficulty budget is split
+    /// between their waves.
+    pub fn new(
+        map_index: usize,
+        difficulty: f32,
+        spawn_points: &[SpawnPointDef],
+        prefabs: &HashMap<String, Prefab>,
+    ) -> Room {
         Room {
             map_index,
-            enemies_to_spawn: enemies,
+            floor_index: 0,
+            entrance: None,
+            enemies_to_spawn: Room::budget_enemies(difficulty, spawn_points, prefabs),
             items_to_spawn: vec![],
             started: false,
             aberration_completed: false,
@@ -64,22 +79,144 @@ impl Room {
             available_upgrades: Upgrades::weapon_selection(),
             upgrade_chosen: false,
             entities_spawned: false,
+            exits_locked: false,
+            wave: 1,
         }
     }
 
+    /// Splits the difficulty evenly between the waves that have spawn points
+    /// without a count. A wave only gets enemies one of its spawn points
+    /// allows.
+    pub fn budget_enemies(
+        difficulty: f32,
+        spawn_points: &[SpawnPointDef],
+        prefabs: &HashMap<String, Prefab>,
+    ) -> HashMap<usize, Vec<String>> {
+        let budget_points = spawn_points
+            .iter()
+            .filter(|spawn_point| spawn_point.count.is_none())
+            .collect::<Vec<_>>();
+        let mut waves = budget_points
+            .iter()
+            .map(|spawn_point| spawn_point.wave)
+            .collect::<Vec<_>>();
+        waves.sort();
+        waves.dedup();
+
+        let regular = regular_enemies(prefabs);
+        let wave_difficulty = difficulty / waves.len().max(1) as f32;
+        waves
+            .iter()
+            .map(|wave| {
+                let choices = regular
+                    .iter()
+                    .filter(|(enemy, _)| {
+                        budget_points.iter().any(|spawn_point| {
+                            spawn_point.wave == *wave && spawn_point.allows(enemy)
+                        })
+                    })
+                    .copied()
+                    .collect::<Vec<_>>();
+                (*wave, Room::random_enemies(wave_difficulty, &choices))
+            })
+            .collect()
+    }
+
+    /// Random enemies adding up to about the difficulty, from the enemies and
+    /// their spawn costs.
+    fn random_enemies(difficulty: f32, enemy_values: &[(&str, f32)]) -> Vec<String> {
+        let mut remaining_difficulty = difficulty;
+        let mut enemies = vec![];
+
+        while remaining_difficulty > 1. {
+            let affordable = enemy_values
+                .iter()
+                .filter(|(_, cost)| *cost <= remaining_difficulty)
+                .collect::<Vec<_>>();
+            if affordable.is_empty() {
+                break;
+            }
+            let (enemy, cost) = affordable[rand::gen_range(0, affordable.len())];
+            enemies.push(enemy.to_string());
+            remaining_difficulty -= cost;
+        }
+        enemies
+    }
+
     pub fn check_completed(&mut self, ecs: &Ecs) {
-        let enemy_entities = ecs.check_components(|e, comps| comps.enemies.contains_key(e));
         self.completed = self.started
             && self.entities_spawned
             && self.upgrade_chosen
-            && enemy_entities.len() == 0;
+            && !self.exits_locked
+            && ecs.components.enemies.is_empty()
+            && ecs.query_ref::<&Spawner>().all(|spawner| !spawner.active);
     }
 
     pub fn despawn(&self, ecs: &mut Ecs) {
-        let room_entities = ecs.check_components(|e, comps| comps.room_entity.contains_key(e));
+        let room_entities = ecs
+            .query_ref::<(Entity, &RoomEntity)>()
+            .map(|(e, _)| e)
+            .collect::<Vec<_>>();
 
         for room_e in room_entities {
             ecs.despawn(room_e);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use macroquad::math::Vec2;
+    use nanoserde::DeJson;
+
+    use super::*;
+
+    fn prefabs() -> HashMap<String, Prefab> {
+        [("hopper", 1.), ("spitter", 2.)]
+            .into_iter()
+            .map(|(name, cost)| {
+                let json = format!(r#"{{"enemy": true, "spawn_cost": {}}}"#, cost);
+                (name.to_string(), Prefab::deserialize_json(&json).unwrap())
+            })
+            .collect()
+    }
+
+    fn spawn_point(enemies: &[&str], wave: usize, count: Option<usize>) -> SpawnPointDef {
+        SpawnPointDef {
+            position: Vec2::ZERO,
+            enemies: enemies.iter().map(|enemy| enemy.to_string()).collect(),
+            wave,
+            delay: 0.,
+            count,
+        }
+    }
+
+    #[test]
+    fn splits_the_budget_between_the_waves() {
+        let spawn_points = [
+            spawn_point(&[], 1, None),
+            spawn_point(&["spitter"], 2, None),
+            spawn_point(&["hopper"], 3, Some(2)),
+        ];
+        let budget = Room::budget_enemies(20., &spawn_points, &prefabs());
+
+        // Spawn points with a count bring their own enemies
+        assert_eq!(budget.len(), 2);
+        let wave_cost = |wave| {
+            budget[&wave]
+                .iter()
+                .map(|enemy| if enemy == "spitter" { 2. } else { 1. })
+                .sum::<f32>()
+        };
+        assert!(wave_cost(1) >= 9. && wave_cost(1) <= 10.);
+        assert!(wave_cost(2) > 8. && wave_cost(2) <= 10.);
+        assert!(budget[&2].iter().all(|enemy| enemy == "spitter"));
+    }
+
+    #[test]
+    fn no_budget_without_spawn_points_taking_from_it() {
+        let spawn_points = [spawn_point(&["spitter"], 1, Some(2))];
+        assert!(Room::budget_enemies(20., &spawn_points, &prefabs()).is_empty());
+        assert!(Room::budget_enemies(20., &[], &prefabs()).is_empty());
+    }
+}
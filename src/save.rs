@@ -0,0 +1,202 @@
+use nanoserde::{DeJson, SerJson};
+
+use crate::{
+    entity::{
+        entities::Ecs,
+        player::{spawn_player, PlayerData},
+        tags::Health,
+        upgrades::{BallsUpgrade, CommonUpgrade, DashUpgrade, LauncherUpgrade, Upgrades},
+    },
+    floor::{Direction, Floor},
+    game_data::GameData,
+    game_state::GameState,
+    items::weapon::{Balls, Dash, Launcher, Weapon},
+    map::map::Map,
+};
+
+/// Bumped whenever the save format changes. Saves of other versions are
+/// ignored instead of being migrated.
+pub const SAVE_VERSION: u32 = 3;
+
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_PATH: &str = "save.json";
+
+#[derive(SerJson, DeJson)]
+pub struct SaveData {
+    pub version: u32,
+    /// Rooms entered before the one the run continues in, they make the
+    /// rooms after it harder.
+    pub completed_rooms: usize,
+    /// The upgrade of the current room was already taken before quitting.
+    pub upgrade_chosen: bool,
+    /// The run continues in the floor's current room.
+    pub floor: Floor,
+    /// Names of the maps the rooms' `map_index` refers to, the list of maps
+    /// may have changed since.
+    pub map_names: Vec<String>,
+    /// Door the player came into the current room through.
+    pub entrance: Option<Direction>,
+    pub weapon: SavedWeapon,
+    pub player: SavedPlayer,
+    pub item_drop_chance_increase: i32,
+    pub upgrades: Upgrades,
+}
+
+#[derive(SerJson, DeJson)]
+pub enum SavedWeapon {
+    Launcher(Vec<LauncherUpgrade>),
+    Balls(Vec<BallsUpgrade>),
+    Dash(Vec<DashUpgrade>),
+}
+
+#[derive(SerJson, DeJson)]
+pub struct SavedPlayer {
+    pub hp: f32,
+    pub aberration: f32,
+    pub upgrades: Vec<CommonUpgrade>,
+}
+
+impl SavedWeapon {
+    fn new(weapon: &Weapon) -> Self {
+        match weapon {
+            Weapon::Launcher(launcher) => SavedWeapon::Launcher(launcher.upgrades.clone()),
+            Weapon::Balls(balls) => SavedWeapon::Balls(balls.upgrades.clone()),
+            Weapon::Dash(dash) => SavedWeapon::Dash(dash.upgrades.clone()),
+        }
+    }
+
+    fn restore(self) -> Weapon {
+        match self {
+            SavedWeapon::Launcher(upgrades) => {
+                let mut launcher = Launcher::new();
+                launcher.upgrades = upgrades;
+                Weapon::Launcher(launcher)
+            }
+            SavedWeapon::Balls(upgrades) => {
+                let mut balls = Balls::new();
+                balls.upgrades = upgrades;
+                Weapon::Balls(balls)
+            }
+            SavedWeapon::Dash(upgrades) => {
+                let mut dash = Dash::new();
+                dash.upgrades = upgrades;
+                Weapon::Dash(dash)
+            }
+        }
+    }
+}
+
+/// Saves the run so it can be continued in the current room of the floor,
+/// after the given amount of rooms.
+pub fn save_run(data: &mut GameData, ecs: &Ecs, completed_rooms: usize, upgrade_chosen: bool) {
+    let Some((player_data, health)) = ecs.query_ref::<(&PlayerData, &Health)>().next() else {
+        return;
+    };
+
+    let save = SaveData {
+        version: SAVE_VERSION,
+        completed_rooms,
+        upgrade_chosen,
+        floor: data.floor.clone(),
+        map_names: data.maps.iter().map(|map| map.name.clone()).collect(),
+        entrance: data.current_room.entrance,
+        weapon: SavedWeapon::new(&data.weapon),
+        player: SavedPlayer {
+            hp: health.hp,
+            aberration: player_data.aberration,
+            upgrades: player_data.upgrades.clone(),
+        },
+        item_drop_chance_increase: data.item_drop_chance_increase,
+        upgrades: data.upgrades.clone(),
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    match std::fs::write(SAVE_PATH, save.serialize_json()) {
+        Ok(()) => data.has_save = true,
+        Err(e) => eprintln!("could not write save: {}", e),
+    }
+    // There is nowhere to save to on the web
+    #[cfg(target_arch = "wasm32")]
+    let _ = save;
+}
+
+/// Saves when quitting, a room that isn't cleared yet is played again when
+/// continuing.
+pub fn save_on_quit(data: &mut GameData, ecs: &Ecs) {
+    if data.state != GameState::Playing || data.dead || data.game_completed {
+        return;
+    }
+    // Halfway into the next room, the save of the last one still holds
+    if data.map_change_requested {
+        return;
+    }
+
+    // Entering the room again counts it again
+    let completed_rooms = if data.floor.current().cleared {
+        data.completed_rooms
+    } else {
+        data.completed_rooms.saturating_sub(1)
+    };
+    let upgrade_chosen = data.current_room.upgrade_chosen;
+    save_run(data, ecs, completed_rooms, upgrade_chosen);
+}
+
+pub fn delete_save(data: &mut GameData) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = std::fs::remove_file(SAVE_PATH);
+    data.has_save = false;
+}
+
+pub fn has_save(maps: &[Map]) -> bool {
+    load_save(maps).is_some()
+}
+
+/// Points the rooms of the saved floor at `maps`, saves with rooms whose map
+/// is gone are ignored.
+pub fn load_save(maps: &[Map]) -> Option<SaveData> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let json = std::fs::read_to_string(SAVE_PATH).ok()?;
+        let mut save = SaveData::deserialize_json(&json).ok()?;
+        if save.version != SAVE_VERSION || save.floor.current >= save.floor.rooms.len() {
+            return None;
+        }
+        for room in &mut save.floor.rooms {
+            let name = save.map_names.get(room.map_index)?;
+            room.map_index = maps.iter().position(|map| &map.name == name)?;
+        }
+        Some(save)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = maps;
+        None
+    }
+}
+
+/// Continues the run from the save, in the room of the floor it was saved in.
+pub fn continue_run(data: &mut GameData, ecs: &mut Ecs, save: SaveData) {
+    data.reset();
+    let player = spawn_player(data, ecs);
+
+    data.weapon = save.weapon.restore();
+    data.item_drop_chance_increase = save.item_drop_chance_increase;
+    data.upgrades = save.upgrades;
+    data.completed_rooms = save.completed_rooms;
+
+    if let Some(player_data) = ecs.components.player_data.get_mut(&player) {
+        player_data.aberration = save.player.aberration;
+        player_data.upgrades = save.player.upgrades;
+    }
+    if let Some(health) = ecs.components.health.get_mut(&player) {
+        health.hp = save.player.hp;
+    }
+
+    data.floor = save.floor;
+    data.enter_room(ecs, data.floor.current, save.entrance);
+    // Cleared rooms have nothing left to choose either way
+    if let Some(next_room) = &mut data.next_room {
+        next_room.upgrade_chosen |= save.upgrade_chosen;
+    }
+    data.state = GameState::Playing;
+}
@@ -0,0 +1,428 @@
+use crate::{
+    entity::entities::Ecs,
+    game_data::GameData,
+    game_state::GameState,
+    time::{Time, FIXED_DT},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Input,
+    PreUpdate,
+    Update,
+    Physics,
+    PostUpdate,
+    Render,
+    UI,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 7] = [
+        Stage::Input,
+        Stage::PreUpdate,
+        Stage::Update,
+        Stage::Physics,
+        Stage::PostUpdate,
+        Stage::Render,
+        Stage::UI,
+    ];
+
+    /// Stages that run once per fixed gameplay step, possibly several times
+    /// or not at all in a frame.
+    pub const FIXED: [Stage; 3] = [Stage::Update, Stage::Physics, Stage::PostUpdate];
+
+    /// Despawns are flushed once these stages are done, so the following
+    /// stages never see entities that were despawned before them.
+    pub fn is_sync_point(&self) -> bool {
+        matches!(self, Stage::PreUpdate | Stage::Update | Stage::PostUpdate)
+    }
+}
+
+/// What the schedule needs from the data its systems run on.
+pub trait ScheduleData {
+    fn time(&mut self) -> &mut Time;
+    fn state(&self) -> &GameState;
+}
+
+impl ScheduleData for GameData {
+    fn time(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    fn state(&self) -> &GameState {
+        &self.state
+    }
+}
+
+pub struct System<D = GameData> {
+    name: &'static str,
+    stage: Stage,
+    states: Vec<GameState>,
+    condition: Option<fn(&D) -> bool>,
+    after: Vec<&'static str>,
+    before: Vec<&'static str>,
+    run: Box<dyn FnMut(&mut D, &mut Ecs)>,
+}
+
+impl System {
+    pub fn new(
+        name: &'static str,
+        stage: Stage,
+        run: impl FnMut(&mut GameData, &mut Ecs) + 'static,
+    ) -> Self {
+        Self::build(name, stage, run)
+    }
+}
+
+impl<D: ScheduleData> System<D> {
+    /// Like [`System::new`], for schedules over other data such as the tests.
+    fn build(
+        name: &'static str,
+        stage: Stage,
+        run: impl FnMut(&mut D, &mut Ecs) + 'static,
+    ) -> Self {
+        Self {
+            name,
+            stage,
+            states: vec![],
+            condition: None,
+            after: vec![],
+            before: vec![],
+            run: Box::new(run),
+        }
+    }
+
+    /// Only runs the system in the given state. Can be called more than once,
+    /// without it the system runs in every state.
+    pub fn in_state(mut self, state: GameState) -> Self {
+        self.states.push(state);
+        self
+    }
+
+    pub fn run_if(mut self, condition: fn(&D) -> bool) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    pub fn after(mut self, name: &'static str) -> Self {
+        self.after.push(name);
+        self
+    }
+
+    pub fn before(mut self, name: &'static str) -> Self {
+        self.before.push(name);
+        self
+    }
+
+    fn should_run(&self, data: &D) -> bool {
+        (self.states.is_empty() || self.states.contains(data.state()))
+            && self.condition.map_or(true, |condition| condition(data))
+    }
+}
+
+pub struct Schedule<D = GameData> {
+    systems: Vec<System<D>>,
+    order: Vec<usize>,
+    dirty: bool,
+}
+
+impl<D> Default for Schedule<D> {
+    fn default() -> Self {
+        Self {
+            systems: vec![],
+            order: vec![],
+            dirty: false,
+        }
+    }
+}
+
+impl<D: ScheduleData> Schedule<D> {
+    pub fn add(&mut self, system: System<D>) -> &mut Self {
+        if self.systems.iter().any(|s| s.name == system.name) {
+            panic!("system `{}` was added twice", system.name);
+        }
+        self.systems.push(system);
+        self.dirty = true;
+        self
+    }
+
+    /// Runs one frame. The fixed stages are stepped as often as the frame time
+    /// calls for, between `PreUpdate` and `Render`. Their `dt` is always
+    /// `FIXED_DT`, the time scale changes how many steps run instead.
+    pub fn run(&mut self, data: &mut D, ecs: &mut Ecs, frame_time: f32) {
+        if self.dirty {
+            self.sort();
+        }
+
+        data.time().dt = frame_time;
+        self.run_stage(Stage::Input, data, ecs);
+        self.run_stage(Stage::PreUpdate, data, ecs);
+
+        let steps = data.time().advance(frame_time);
+        data.time().dt = FIXED_DT;
+        for _ in 0..steps {
+            for stage in Stage::FIXED {
+                self.run_stage(stage, data, ecs);
+            }
+        }
+
+        data.time().dt = frame_time;
+        self.run_stage(Stage::Render, data, ecs);
+        self.run_stage(Stage::UI, data, ecs);
+
+        // Events sent by the fixed stages are read by the next step. Swapping
+        // the queues on a frame without one would drop them unread, e.g. at
+        // frame rates above the step rate
+        if steps > 0 {
+            ecs.events.update();
+        }
+    }
+
+    fn run_stage(&mut self, stage: Stage, data: &mut D, ecs: &mut Ecs) {
+        for index in &self.order {
+            let system = &mut self.systems[*index];
+            if system.stage == stage && system.should_run(data) {
+                (system.run)(data, ecs);
+            }
+        }
+
+        if stage.is_sync_point() {
+            ecs.flush();
+        }
+    }
+
+    /// Orders the systems by stage, then by their `after`/`before`
+    /// constraints. Systems without constraints keep the order they were
+    /// added in. Constraints may only name systems of the same stage.
+    fn sort(&mut self) {
+        self.check_constraints();
+        self.order.clear();
+
+        for stage in Stage::ALL {
+            let mut pending = (0..self.systems.len())
+                .filter(|i| self.systems[*i].stage == stage)
+                .collect::<Vec<_>>();
+
+            while !pending.is_empty() {
+                let ready = pending.iter().position(|i| {
+                    pending
+                        .iter()
+                        .all(|other| other == i || !self.must_run_before(*other, *i))
+                });
+
+                match ready {
+                    Some(index) => self.order.push(pending.remove(index)),
+                    None => {
+                        let names = pending
+                            .iter()
+                            .map(|i| self.systems[*i].name)
+                            .collect::<Vec<_>>();
+                        panic!("cyclic system ordering between {:?}", names);
+                    }
+                }
+            }
+        }
+
+        self.dirty = false;
+    }
+
+    /// Panics on constraints that could never hold, like `add` does on
+    /// duplicate names, so a typo doesn't silently drop an ordering.
+    fn check_constraints(&self) {
+        for system in &self.systems {
+            let constraints = system
+                .after
+                .iter()
+                .map(|name| ("after", name))
+                .chain(system.before.iter().map(|name| ("before", name)));
+            for (kind, name) in constraints {
+                match self.systems.iter().find(|other| other.name == *name) {
+                    None => panic!(
+                        "system `{}` runs {} unknown system `{}`",
+                        system.name, kind, name
+                    ),
+                    Some(other) if other.stage != system.stage => panic!(
+                        "system `{}` in {:?} runs {} `{}` in {:?}",
+                        system.name, system.stage, kind, name, other.stage
+                    ),
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    fn must_run_before(&self, first: usize, second: usize) -> bool {
+        let first = &self.systems[first];
+        let second = &self.systems[second];
+        second.after.contains(&first.name) || first.before.contains(&second.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::events::{CollisionStarted, EventReader};
+
+    #[derive(Default)]
+    struct TestData {
+        time: Time,
+        state: GameState,
+        /// Name and `dt` of every system run.
+        runs: Vec<(&'static str, f32)>,
+        collisions_read: usize,
+    }
+
+    impl ScheduleData for TestData {
+        fn time(&mut self) -> &mut Time {
+            &mut self.time
+        }
+
+        fn state(&self) -> &GameState {
+            &self.state
+        }
+    }
+
+    fn recorder(name: &'static str, stage: Stage) -> System<TestData> {
+        System::build(name, stage, move |data: &mut TestData, _| {
+            let dt = data.time.dt;
+            data.runs.push((name, dt));
+        })
+    }
+
+    #[test]
+    fn steps_fixed_stages_as_often_as_the_frame_time_calls_for() {
+        let mut schedule = Schedule::<TestData>::default();
+        for (name, stage) in [
+            ("input", Stage::Input),
+            ("update", Stage::Update),
+            ("physics", Stage::Physics),
+            ("render", Stage::Render),
+        ] {
+            schedule.add(recorder(name, stage));
+        }
+        let mut data = TestData::default();
+        let mut ecs = Ecs::default();
+
+        let frame_time = FIXED_DT * 2.5;
+        schedule.run(&mut data, &mut ecs, frame_time);
+
+        let names = data.runs.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["input", "update", "physics", "update", "physics", "render"]
+        );
+        assert_eq!(data.runs[0].1, frame_time);
+        assert_eq!(data.runs[1].1, FIXED_DT);
+        assert_eq!(data.runs[5].1, frame_time);
+        assert!((data.time.alpha - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn fixed_stage_events_survive_frames_without_a_step() {
+        let mut schedule = Schedule::<TestData>::default();
+        let mut sent = false;
+        let mut reader = EventReader::<CollisionStarted>::default();
+        schedule
+            .add(System::build(
+                "read",
+                Stage::Update,
+                move |data: &mut TestData, ecs| {
+                    data.collisions_read += reader.read(ecs.events.get()).count();
+                },
+            ))
+            .add(System::build("send", Stage::Physics, move |_, ecs| {
+                if !sent {
+                    let entity = ecs.spawn();
+                    ecs.events.send(CollisionStarted(entity, entity));
+                    sent = true;
+                }
+            }));
+        let mut data = TestData::default();
+        let mut ecs = Ecs::default();
+
+        schedule.run(&mut data, &mut ecs, FIXED_DT);
+        assert_eq!(data.collisions_read, 0);
+        // Too short for a step
+        schedule.run(&mut data, &mut ecs, FIXED_DT * 0.25);
+        schedule.run(&mut data, &mut ecs, FIXED_DT);
+        assert_eq!(data.collisions_read, 1);
+    }
+
+    fn system(name: &'static str, stage: Stage) -> System<TestData> {
+        System::build(name, stage, |_, _| {})
+    }
+
+    fn sorted_names(schedule: &mut Schedule<TestData>) -> Vec<&'static str> {
+        schedule.sort();
+        schedule
+            .order
+            .iter()
+            .map(|i| schedule.systems[*i].name)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_insertion_order_within_a_stage() {
+        let mut schedule = Schedule::<TestData>::default();
+        schedule
+            .add(system("render", Stage::Render))
+            .add(system("a", Stage::Update))
+            .add(system("b", Stage::Update))
+            .add(system("input", Stage::Input));
+
+        assert_eq!(sorted_names(&mut schedule), ["input", "a", "b", "render"]);
+    }
+
+    #[test]
+    fn orders_by_after_and_before() {
+        let mut schedule = Schedule::<TestData>::default();
+        schedule
+            .add(system("a", Stage::Update).after("c"))
+            .add(system("b", Stage::Update))
+            .add(system("c", Stage::Update))
+            .add(system("d", Stage::Update).before("b"));
+
+        assert_eq!(sorted_names(&mut schedule), ["c", "a", "d", "b"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cyclic system ordering")]
+    fn panics_on_cycles() {
+        let mut schedule = Schedule::<TestData>::default();
+        schedule
+            .add(system("a", Stage::Update).after("b"))
+            .add(system("b", Stage::Update).after("c"))
+            .add(system("c", Stage::Update).after("a"));
+
+        schedule.sort();
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown system `missing`")]
+    fn panics_on_unknown_names() {
+        let mut schedule = Schedule::<TestData>::default();
+        schedule.add(system("a", Stage::Update).before("missing"));
+
+        schedule.sort();
+    }
+
+    #[test]
+    #[should_panic(expected = "runs after `a` in Update")]
+    fn panics_on_constraints_across_stages() {
+        let mut schedule = Schedule::<TestData>::default();
+        schedule
+            .add(system("a", Stage::Update))
+            .add(system("b", Stage::Render).after("a"));
+
+        schedule.sort();
+    }
+
+    #[test]
+    #[should_panic(expected = "added twice")]
+    fn panics_on_duplicate_names() {
+        let mut schedule = Schedule::<TestData>::default();
+        schedule
+            .add(system("a", Stage::Update))
+            .add(system("a", Stage::Render));
+    }
+}
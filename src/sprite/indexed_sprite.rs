@@ -4,23 +4,22 @@ use crate::game_data::GameData;
 
 #[derive(Clone)]
 pub struct IndexedSprite {
-    pub texture: &'static str,
+    pub texture: String,
     position_offset: Vec2,
     frame_width: u32,
     row_len: usize,
 }
 
 impl IndexedSprite {
-    pub fn new(
-        data: &GameData,
-        texture: &'static str,
-        frame_width: u32,
-        position_offset: Vec2,
-    ) -> Self {
-        let tex = data.graphics.textures.get(texture).unwrap();
+    pub fn new(data: &GameData, texture: &str, frame_width: u32, position_offset: Vec2) -> Self {
+        let tex = data
+            .graphics
+            .textures
+            .get(texture)
+            .unwrap_or_else(|| panic!("unknown texture `{}`", texture));
         let row_len = (tex.width() / frame_width as f32).trunc() as usize;
         Self {
-            texture,
+            texture: texture.to_owned(),
             frame_width,
             row_len,
             position_offset,
@@ -40,7 +39,7 @@ impl IndexedSprite {
         flipped: bool,
     ) {
         let pos = pos - self.position_offset;
-        let tex = data.graphics.textures.get(self.texture).unwrap();
+        let tex = data.graphics.textures.get(&self.texture).unwrap();
         draw_texture_ex(
             &tex,
             pos.x,
@@ -58,7 +57,7 @@ impl IndexedSprite {
     pub fn texture_source(&self, data: &GameData, index: usize) -> Rect {
         let x = index % self.row_len * self.frame_width as usize;
         let y = index / self.row_len * self.frame_width as usize;
-        let tex = data.graphics.textures.get(self.texture).unwrap();
+        let tex = data.graphics.textures.get(&self.texture).unwrap();
         Rect::new(x as f32, y as f32, self.frame_width as f32, tex.height())
     }
 }
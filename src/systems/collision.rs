@@ -1,8 +1,19 @@
 use macroquad::prelude::*;
+use nanoserde::DeJson;
 
-use crate::{entity::entities::Ecs, game_data::GameData};
+use crate::{
+    entity::{
+        entities::Ecs,
+        entity_id::Entity,
+        events::{CollisionOngoing, CollisionStarted, EventReader},
+        player::PlayerData,
+        spitter::Spitter,
+    },
+    game_data::GameData,
+    physics::collision::raycast,
+};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy, DeJson)]
 pub enum ColliderType {
     Projectile,
     PlayerProjectile,
@@ -11,57 +22,237 @@ pub enum ColliderType {
     Player,
     Enemy,
     Map,
+    Trigger,
+}
+
+/// Pairs of collider types that collide, in either order. Everything not
+/// listed passes through each other.
+///
+/// Enemy shots (`Projectile`) and enemies are left out on purpose. The old
+/// per-type rules only listed the pair from the enemy's side, and since a
+/// collider was only checked against the ones spawned before it, it only
+/// applied to enemies spawned after the shot. Listing it in both orders
+/// would make a spitter's shot hit the spitter it comes out of.
+const COLLISION_MATRIX: [(ColliderType, ColliderType); 13] = [
+    (ColliderType::Projectile, ColliderType::PlayerProjectile),
+    (
+        ColliderType::Projectile,
+        ColliderType::ProjectileWithoutMapCollision,
+    ),
+    (ColliderType::Projectile, ColliderType::Player),
+    (ColliderType::Projectile, ColliderType::Map),
+    (ColliderType::PlayerProjectile, ColliderType::Enemy),
+    (ColliderType::PlayerProjectile, ColliderType::Map),
+    (
+        ColliderType::ProjectileWithoutMapCollision,
+        ColliderType::Enemy,
+    ),
+    (ColliderType::Pickup, ColliderType::Player),
+    (ColliderType::Player, ColliderType::Enemy),
+    (ColliderType::Player, ColliderType::Map),
+    (ColliderType::Enemy, ColliderType::Enemy),
+    (ColliderType::Enemy, ColliderType::Map),
+    (ColliderType::Player, ColliderType::Trigger),
+];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CollisionResponse {
+    /// Pushes the colliders apart.
+    Solid,
+    /// Only reports the collision.
+    Overlap,
 }
 
 impl ColliderType {
-    // TODO: use collision matrix
-    pub fn should_collide(&self, other: &ColliderType) -> bool {
+    pub fn layer(&self) -> u32 {
+        1 << *self as u32
+    }
+
+    /// Layers this type collides with, according to `COLLISION_MATRIX`.
+    pub fn mask(&self) -> u32 {
+        COLLISION_MATRIX
+            .iter()
+            .filter_map(|(a, b)| {
+                if a == self {
+                    Some(b.layer())
+                } else if b == self {
+                    Some(a.layer())
+                } else {
+                    None
+                }
+            })
+            .fold(0, |mask, layer| mask | layer)
+    }
+
+    pub fn response(&self) -> CollisionResponse {
+        match self {
+            ColliderType::Player | ColliderType::Enemy | ColliderType::Map => {
+                CollisionResponse::Solid
+            }
+            ColliderType::Projectile
+            | ColliderType::PlayerProjectile
+            | ColliderType::ProjectileWithoutMapCollision
+            | ColliderType::Pickup
+            | ColliderType::Trigger => CollisionResponse::Overlap,
+        }
+    }
+}
+
+/// Shapes are relative to the entity's position.
+#[derive(Debug, Clone)]
+pub enum Shape {
+    Circle {
+        radius: f32,
+    },
+    Aabb {
+        half_size: Vec2,
+    },
+    /// Segment from `a` to `b` grown by `radius`.
+    Capsule {
+        a: Vec2,
+        b: Vec2,
+        radius: f32,
+    },
+    /// Convex, the points go around the outline in order.
+    Polygon {
+        points: Vec<Vec2>,
+    },
+}
+
+impl Shape {
+    /// Radius of a circle around the position that contains the shape.
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            Shape::Circle { radius } => *radius,
+            Shape::Aabb { half_size } => half_size.length(),
+            Shape::Capsule { a, b, radius } => a.length().max(b.length()) + radius,
+            Shape::Polygon { points } => points.iter().map(|p| p.length()).fold(0., f32::max),
+        }
+    }
+
+    /// The shape as a convex outline grown by a radius. Circles are a single
+    /// point, capsules a segment.
+    pub fn core(&self) -> (Vec<Vec2>, f32) {
+        match self {
+            Shape::Circle { radius } => (vec![Vec2::ZERO], *radius),
+            Shape::Aabb { half_size } => (
+                vec![
+                    vec2(-half_size.x, -half_size.y),
+                    vec2(half_size.x, -half_size.y),
+                    vec2(half_size.x, half_size.y),
+                    vec2(-half_size.x, half_size.y),
+                ],
+                0.,
+            ),
+            Shape::Capsule { a, b, radius } => (vec![*a, *b], *radius),
+            Shape::Polygon { points } => (points.clone(), 0.),
+        }
+    }
+
+    pub fn draw(&self, pos: Vec2, color: Color) {
         match self {
-            ColliderType::Projectile => match other {
-                ColliderType::PlayerProjectile
-                | ColliderType::ProjectileWithoutMapCollision
-                | ColliderType::Player
-                | ColliderType::Map => true,
-                _ => false,
-            },
-            ColliderType::PlayerProjectile => match other {
-                ColliderType::Projectile | ColliderType::Enemy | ColliderType::Map => true,
-                _ => false,
-            },
-            ColliderType::ProjectileWithoutMapCollision => match other {
-                ColliderType::Projectile | ColliderType::Enemy => true,
-                _ => false,
-            },
-            ColliderType::Pickup => match other {
-                ColliderType::Player => true,
-                _ => false,
-            },
-            ColliderType::Player => match other {
-                ColliderType::Enemy
-                | ColliderType::Pickup
-                | ColliderType::Projectile
-                | ColliderType::Map => true,
-                _ => false,
-            },
-            ColliderType::Enemy => match other {
-                ColliderType::Player
-                | ColliderType::Enemy
-                | ColliderType::Projectile
-                | ColliderType::ProjectileWithoutMapCollision
-                | ColliderType::Map => true,
-                _ => false,
-            },
-            ColliderType::Map => match other {
-                ColliderType::Enemy | ColliderType::Player | ColliderType::Projectile => true,
-                _ => false,
-            },
+            Shape::Circle { radius } => draw_circle_lines(pos.x, pos.y, *radius, 1., color),
+            Shape::Aabb { half_size } => draw_rectangle_lines(
+                pos.x - half_size.x,
+                pos.y - half_size.y,
+                half_size.x * 2.,
+                half_size.y * 2.,
+                1.,
+                color,
+            ),
+            Shape::Capsule { a, b, radius } => {
+                let (a, b) = (pos + *a, pos + *b);
+                let side = (b - a).normalize_or_zero().perp() * *radius;
+                draw_circle_lines(a.x, a.y, *radius, 1., color);
+                draw_circle_lines(b.x, b.y, *radius, 1., color);
+                draw_line(
+                    a.x + side.x,
+                    a.y + side.y,
+                    b.x + side.x,
+                    b.y + side.y,
+                    1.,
+                    color,
+                );
+                draw_line(
+                    a.x - side.x,
+                    a.y - side.y,
+                    b.x - side.x,
+                    b.y - side.y,
+                    1.,
+                    color,
+                );
+            }
+            Shape::Polygon { points } => {
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    draw_line(
+                        pos.x + a.x,
+                        pos.y + a.y,
+                        pos.x + b.x,
+                        pos.y + b.y,
+                        1.,
+                        color,
+                    );
+                }
+            }
         }
     }
 }
 
-pub struct CircleCollider {
-    pub radius: f32,
+#[derive(Clone)]
+pub struct Collider {
+    pub shape: Shape,
     pub coll_type: ColliderType,
+    pub layer: u32,
+    pub mask: u32,
+    pub response: CollisionResponse,
+}
+
+impl Collider {
+    /// Takes layer, mask and response from the collider type.
+    pub fn new(shape: Shape, coll_type: ColliderType) -> Self {
+        Self {
+            shape,
+            coll_type,
+            layer: coll_type.layer(),
+            mask: coll_type.mask(),
+            response: coll_type.response(),
+        }
+    }
+
+    pub fn circle(radius: f32, coll_type: ColliderType) -> Self {
+        Self::new(Shape::Circle { radius }, coll_type)
+    }
+
+    /// Both colliders have to accept the other one's layer.
+    pub fn collides_with(&self, other: &Collider) -> bool {
+        self.mask & other.layer != 0 && other.mask & self.layer != 0
+    }
+
+    pub fn collides_with_map(&self) -> bool {
+        self.mask & ColliderType::Map.layer() != 0
+    }
+
+    /// Colliders are only pushed apart if both of them are solid.
+    pub fn is_solid_with(&self, other: &Collider) -> bool {
+        self.response == CollisionResponse::Solid && other.response == CollisionResponse::Solid
+    }
+}
+
+/// Pairs that started or kept touching in the physics steps since the last
+/// read, for systems that act for as long as a contact lasts.
+pub fn read_contacts(
+    ecs: &Ecs,
+    started_reader: &mut EventReader<CollisionStarted>,
+    ongoing_reader: &mut EventReader<CollisionOngoing>,
+) -> Vec<(Entity, Entity)> {
+    let started = started_reader
+        .read(ecs.events.get::<CollisionStarted>())
+        .map(|e| (e.0, e.1));
+    let ongoing = ongoing_reader
+        .read(ecs.events.get::<CollisionOngoing>())
+        .map(|e| (e.0, e.1));
+    started.chain(ongoing).collect()
 }
 
 pub fn draw_colliders(data: &GameData, ecs: &Ecs) {
@@ -69,14 +260,41 @@ pub fn draw_colliders(data: &GameData, ecs: &Ecs) {
         return;
     }
 
-    let colliders = ecs.check_components(|e, comps| {
-        comps.positions.contains_key(e) && comps.colliders.contains_key(e)
-    });
-
-    for id in &colliders {
-        let pos = ecs.components.positions.get(&id).unwrap();
-        let coll = ecs.components.colliders.get(&id).unwrap();
+    for (pos, coll) in ecs.query_ref::<(&Vec2, &Collider)>() {
+        coll.shape.draw(*pos, BLUE)
+    }
 
-        draw_circle_lines(pos.x, pos.y, coll.radius, 1., BLUE)
+    // What the spitters see of the player
+    let Some((player_e, player_pos)) = ecs
+        .query_ref::<(Entity, &PlayerData, &Vec2)>()
+        .map(|(e, _, pos)| (e, *pos))
+        .next()
+    else {
+        return;
+    };
+    let mask = ColliderType::Map.layer() | ColliderType::Player.layer();
+    for (_, pos) in ecs.query_ref::<(&Spitter, &Vec2)>() {
+        let from = *pos + vec2(0., -5.);
+        match raycast(ecs, data.current_map(), from, player_pos, mask) {
+            Some(hit) if hit.entity == player_e => {
+                draw_line(from.x, from.y, hit.point.x, hit.point.y, 1., GREEN)
+            }
+            Some(hit) => {
+                draw_line(from.x, from.y, hit.point.x, hit.point.y, 1., RED);
+                let normal_end = hit.point + hit.normal * 4.;
+                draw_line(
+                    hit.point.x,
+                    hit.point.y,
+                    normal_end.x,
+                    normal_end.y,
+                    1.,
+                    ORANGE,
+                );
+                if let Some((x, y)) = hit.tile {
+                    draw_rectangle_lines(x as f32 * 8., y as f32 * 8., 8., 8., 1., RED);
+                }
+            }
+            None => {}
+        }
     }
 }
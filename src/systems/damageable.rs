@@ -1,53 +1,45 @@
-use std::collections::HashMap;
-
 use crate::{
     entity::{
         entities::Ecs,
         entity_id::Entity,
-        events::{DamageEvent, DeathEvent},
+        events::{
+            BossPhaseChanged, CollisionOngoing, CollisionStarted, DamageEvent, DeathEvent,
+            EventReader, PickupCollected,
+        },
         impact::{spawn_dust, splatter_blood},
-        mirituhg::{self, spawn_mirituhg_death, MiritughState},
+        mirituhg::{self, MiritughState},
         pickup::{spawn_pickup, Pickup},
+        player::PlayerData,
+        prefab::spawn_prefab,
         projectile::spawn_bullet,
         skull::spawn_skull,
-        tags::EntityType,
+        tags::{Damageable, DespawnOnHit, EntityType, Health},
     },
     game_data::GameData,
     items::weapon::Weapon,
-    physics::collision::Collision,
+    save::delete_save,
 };
 use macroquad::{
     audio::{self, PlaySoundParams},
     prelude::*,
 };
 
-use super::collision::ColliderType;
-
-pub fn update_damageables(ecs: &mut Ecs) {
-    let damageables = ecs.check_components(|e, comps| comps.damageables.contains_key(e));
-
-    for damageable_e in &damageables {
-        let damageable = ecs.components.damageables.get_mut(damageable_e).unwrap();
+use super::collision::{read_contacts, ColliderType};
 
+pub fn update_damageables(ecs: &mut Ecs, dt: f32) {
+    for damageable in ecs.query::<&mut Damageable>() {
         if let Some(invulnerable_timer) = &mut damageable.invulnerable_timer {
-            invulnerable_timer.update();
+            invulnerable_timer.update(dt);
         }
 
         if let Some(hit_fx_timer) = &mut damageable.hit_fx_timer {
-            hit_fx_timer.update();
+            hit_fx_timer.update(dt);
         }
     }
 }
 
 pub fn flash_on_damage(ecs: &mut Ecs) {
-    let damageables = ecs.check_components(|e, comps| {
-        comps.damageables.contains_key(e) && comps.materials.contains_key(e)
-    });
-
-    for damageable_e in &damageables {
-        let damageable = ecs.components.damageables.get_mut(damageable_e).unwrap();
-        let material = ecs.components.materials.get_mut(damageable_e).unwrap();
-
+    for (damageable, material) in ecs.query::<(&mut Damageable, &Material)>() {
         if let Some(hit_fx_timer) = &mut damageable.hit_fx_timer {
             if !hit_fx_timer.completed() {
                 let intensity = hit_fx_timer.progress() * 10. + 1.;
@@ -64,12 +56,26 @@ pub fn flash_on_damage(ecs: &mut Ecs) {
     }
 }
 
-pub fn apply_damage(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Vec<DamageEvent>) {
-    let damageables = ecs.check_components(|e, comps| {
-        comps.damageables.contains_key(e) && comps.health.contains_key(e)
-    });
+pub fn apply_damage(
+    data: &mut GameData,
+    ecs: &mut Ecs,
+    damage_reader: &mut EventReader<DamageEvent>,
+) {
+    let mut damage_events = damage_reader
+        .read(ecs.events.get::<DamageEvent>())
+        .copied()
+        .collect::<Vec<_>>();
+    let damageables = ecs
+        .query_ref::<(Entity, &Damageable, &Health)>()
+        .map(|(e, _, _)| e)
+        .collect::<Vec<_>>();
 
     let mut splatter_positions = vec![];
+    let mut knockbacks = vec![];
+
+    // Events can outlive their targets. The source may be gone too, e.g. a
+    // bullet despawned on the same hit, and its damage still lands
+    damage_events.retain(|e| ecs.is_alive(e.target));
 
     for damageable_e in &damageables {
         let damageable = ecs.components.damageables.get_mut(damageable_e).unwrap();
@@ -95,8 +101,8 @@ pub fn apply_damage(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Vec<
         if let Some(event) = event {
             if let Some(invulnerable_timer) = &mut damageable.invulnerable_timer {
                 if invulnerable_timer.completed() {
-                    let is_player = ecs.components.player_data.contains_key(damageable_e);
-                    let is_enemy = ecs.components.enemies.contains_key(damageable_e);
+                    let is_player = ecs.components.player_data.contains(damageable_e);
+                    let is_enemy = ecs.components.enemies.contains(damageable_e);
 
                     let mut apply_damage = true;
                     if is_player {
@@ -115,31 +121,49 @@ pub fn apply_damage(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Vec<
                         }
                     }
                     if is_enemy {
-                        let coll = ecs.components.colliders.get(&event.source).unwrap();
+                        let coll_type = ecs
+                            .components
+                            .colliders
+                            .get(&event.source)
+                            .map(|c| c.coll_type);
 
-                        if coll.coll_type == ColliderType::ProjectileWithoutMapCollision {
+                        if coll_type == Some(ColliderType::ProjectileWithoutMapCollision) {
                             if let Weapon::Balls(ref balls) = data.weapon {
                                 let up_data = balls.get_upgraded_data();
                                 if up_data.bullets {
-                                    let pos = ecs.components.positions.get(&event.target).unwrap();
-                                    bullets.push(*pos);
+                                    if let Some(pos) = ecs.components.positions.get(&event.target) {
+                                        bullets.push(*pos);
+                                    }
                                 }
                             }
                         }
                         if let Weapon::Dash(ref balls) = data.weapon {
                             let up_data = balls.get_upgraded_data();
                             if up_data.bullets {
-                                let pos = ecs.components.positions.get(&event.target).unwrap();
-                                bullets.push(*pos);
+                                if let Some(pos) = ecs.components.positions.get(&event.target) {
+                                    bullets.push(*pos);
+                                }
                             }
                         }
                     }
 
                     if apply_damage {
                         health.hp -= event.damage;
+                        if event.knockback > 0. {
+                            knockbacks.push((event.source, *damageable_e, event.knockback));
+                        }
                         if is_player {
                             data.screen_shake.shake(0.25, 8.);
                         }
+                        if is_enemy && health.hp <= 0. {
+                            if let Weapon::Dash(ref dash) = data.weapon {
+                                if dash.dashing
+                                    && ecs.components.player_data.contains(&event.source)
+                                {
+                                    data.time.hit_stop(0.06);
+                                }
+                            }
+                        }
                     }
 
                     audio::play_sound(
@@ -207,28 +231,52 @@ pub fn apply_damage(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Vec<
         }
     }
 
+    for (source, target, knockback) in knockbacks {
+        let impulse = knockback_direction(ecs, source, target) * knockback;
+        if let Some(body) = ecs.components.physics_bodies.get_mut(&target) {
+            body.apply_impulse(impulse);
+        }
+    }
+
     for pos in &splatter_positions {
         splatter_blood(data, ecs, *pos);
     }
+}
 
-    damage_events.clear();
+/// Moving sources (bullets, a dashing player) push along their direction,
+/// standing ones push away from themselves.
+fn knockback_direction(ecs: &Ecs, source: Entity, target: Entity) -> Vec2 {
+    if let Some(velocity) = ecs.components.velocities.get(&source) {
+        if velocity.0.length_squared() > 0. {
+            return velocity.0.normalize();
+        }
+    }
+    let source_pos = ecs.components.positions.get(&source);
+    let target_pos = ecs.components.positions.get(&target);
+    match (source_pos, target_pos) {
+        (Some(source_pos), Some(target_pos)) => (*target_pos - *source_pos).normalize_or_zero(),
+        _ => Vec2::ZERO,
+    }
 }
 
+/// Contact damage starts with the contact and repeats while it lasts, the
+/// invulnerability time of the target limits how often it hits.
 pub fn damage_on_collision(
-    ecs: &Ecs,
-    damage_events: &mut Vec<DamageEvent>,
-    collisions: &HashMap<(Entity, Entity), Collision>,
+    ecs: &mut Ecs,
+    started_reader: &mut EventReader<CollisionStarted>,
+    ongoing_reader: &mut EventReader<CollisionOngoing>,
 ) {
-    let damageables = ecs.check_components(|e, comps| comps.damageables.contains_key(e));
+    let contacts = read_contacts(ecs, started_reader, ongoing_reader);
+    let mut damage_events = vec![];
 
-    for damageable_e in &damageables {
-        for ((source, target), _collision) in collisions.iter() {
-            if target != damageable_e && source != damageable_e {
+    for (damageable_e, _) in ecs.query_ref::<(Entity, &Damageable)>() {
+        for (source, target) in &contacts {
+            if *target != damageable_e && *source != damageable_e {
                 continue;
             }
             for (e1, e2) in [(source, target), (target, source)] {
                 if let Some(damage_on_coll) = ecs.components.damage_on_collision.get(e1) {
-                    let apply_damage = if ecs.components.player_data.contains_key(e2) {
+                    let apply_damage = if ecs.components.player_data.contains(e2) {
                         damage_on_coll.source == EntityType::Enemy
                     } else {
                         damage_on_coll.source == EntityType::Player
@@ -238,69 +286,63 @@ pub fn damage_on_collision(
                             source: *e1,
                             target: *e2,
                             damage: damage_on_coll.damage,
+                            knockback: damage_on_coll.knockback,
                         });
                     }
                 }
             }
         }
     }
+
+    ecs.events.damage.extend(damage_events);
 }
 
 pub fn despawn_on_collision(
     data: &mut GameData,
     ecs: &mut Ecs,
-    collisions: &HashMap<(Entity, Entity), Collision>,
+    started_reader: &mut EventReader<CollisionStarted>,
+    ongoing_reader: &mut EventReader<CollisionOngoing>,
 ) {
-    let despawn_on_hits = ecs.check_components(|e, comps| comps.despawn_on_hit.contains_key(e));
+    let collisions = read_contacts(ecs, started_reader, ongoing_reader);
+    let despawn_on_hits = ecs
+        .query_ref::<(Entity, &DespawnOnHit)>()
+        .map(|(e, _)| e)
+        .collect::<Vec<_>>();
 
     for despawn_e in &despawn_on_hits {
-        for ((source, target), _collision) in collisions.iter() {
+        // Nothing to put the dust at
+        let Some(position) = ecs.components.positions.get(despawn_e).copied() else {
+            continue;
+        };
+        for (source, target) in &collisions {
             for (e1, e2) in [(source, target), (target, source)] {
                 if e1 == despawn_e {
-                    let despawn_on_hit = ecs.components.despawn_on_hit.get(despawn_e).unwrap();
-                    // TODO: not safe
-                    let position = ecs.components.positions.get(despawn_e).unwrap();
                     // if ecs.components.player_entity.contains_key(e2)
                     //     && despawn_on_hit.0 == EntityType::Player
                     // {
-                    //     spawn_dust(data, ecs, *position);
+                    //     spawn_dust(data, ecs, position);
                     //     ecs.despawn(*despawn_e);
                     //     break;
                     // };
 
-                    if let Some(player) = ecs.components.player_data.get_mut(e2) {
+                    if let Some(player) = ecs.components.player_data.get(e2) {
                         let up_data = player.get_upgraded_data();
                         if let Some(pickup) = ecs.components.pickups.get(despawn_e) {
-                            if match pickup {
-                                Pickup::Health(increase) => {
-                                    let mut low_hp = false;
-                                    if let Some(health) = ecs.components.health.get_mut(e2) {
-                                        low_hp = health.hp < up_data.max_hp as f32;
-                                        if low_hp {
-                                            health.hp =
-                                                (health.hp + increase).min(up_data.max_hp as f32);
-                                        }
-                                    }
-                                    low_hp
-                                }
-                                Pickup::AnomalyBig => {
-                                    player.aberration = (player.aberration - 0.1).max(0.);
-                                    true
-                                }
-                                Pickup::AnomalySmall => {
-                                    player.aberration = (player.aberration - 0.02).max(0.);
-                                    true
-                                }
-                            } {
-                                spawn_dust(data, ecs, *position);
+                            let collect = match pickup {
+                                Pickup::Health(_) => ecs
+                                    .components
+                                    .health
+                                    .get(e2)
+                                    .map_or(false, |health| health.hp < up_data.max_hp as f32),
+                                Pickup::AnomalyBig | Pickup::AnomalySmall => true,
+                            };
+                            if collect {
+                                ecs.events.send(PickupCollected {
+                                    collector: *e2,
+                                    pickup: *pickup,
+                                });
+                                spawn_dust(data, ecs, position);
                                 ecs.despawn(*despawn_e);
-                                audio::play_sound(
-                                    &data.audio.confirm2,
-                                    PlaySoundParams {
-                                        volume: data.settings.sfx_volume * 1.2,
-                                        ..Default::default()
-                                    },
-                                );
                                 break;
                             }
 
@@ -308,7 +350,7 @@ pub fn despawn_on_collision(
                         }
                     }
 
-                    spawn_dust(data, ecs, *position);
+                    spawn_dust(data, ecs, position);
                     ecs.despawn(*despawn_e);
                     break;
                 }
@@ -317,43 +359,56 @@ pub fn despawn_on_collision(
     }
 }
 
-pub fn kill_entities(data: &GameData, ecs: &mut Ecs, death_events: &mut Vec<DeathEvent>) {
-    let healthies = ecs.check_components(|e, comps| comps.health.contains_key(e));
-
-    for health_e in &healthies {
-        let health = ecs.components.health.get_mut(health_e).unwrap();
-
-        if health.hp <= 0. {
-            ecs.despawn(*health_e);
-            death_events.push(DeathEvent(*health_e));
-
-            let aberration_increase = ecs.components.aberration_increase.get(health_e);
-            if let Some(inc) = aberration_increase {
-                let inc = inc * (1. + data.completed_rooms as f32 * 0.37);
-                // let inc = inc * (1. + data.completed_rooms as f32 * 0.07);
-                let players = ecs.check_components(|e, comps| comps.player_data.contains_key(e));
-
-                for player_e in &players {
-                    let player_data = ecs.components.player_data.get_mut(player_e).unwrap();
-                    player_data.aberration = (player_data.aberration + inc).clamp(0., 1.);
-                }
+pub fn kill_entities(data: &mut GameData, ecs: &mut Ecs) {
+    let dead = ecs
+        .query_ref::<(Entity, &Health)>()
+        .filter(|(_, health)| health.hp <= 0.)
+        .map(|(e, _)| e)
+        .collect::<Vec<_>>();
+
+    for health_e in &dead {
+        ecs.despawn(*health_e);
+        ecs.events.send(DeathEvent(*health_e));
+
+        let aberration_increase = ecs.components.aberration_increase.get(health_e);
+        if let Some(inc) = aberration_increase {
+            let inc = inc.0 * (1. + data.completed_rooms as f32 * 0.37);
+            // let inc = inc * (1. + data.completed_rooms as f32 * 0.07);
+            for player_data in ecs.query::<&mut PlayerData>() {
+                player_data.aberration = (player_data.aberration + inc).clamp(0., 1.);
             }
         }
     }
 }
 
-pub fn handle_death(data: &mut GameData, ecs: &mut Ecs, death_events: &Vec<DeathEvent>) {
+pub fn handle_death(
+    data: &mut GameData,
+    ecs: &mut Ecs,
+    death_reader: &mut EventReader<DeathEvent>,
+) {
     let mut skull_positions = vec![];
     let mut pickups = vec![];
 
+    let dead = death_reader
+        .read(ecs.events.get::<DeathEvent>())
+        .map(|e| e.0)
+        .collect::<Vec<_>>();
+
     let mut spawn_death = None;
-    for ev in death_events {
-        let pos = ecs.components.positions.get(&ev.0).unwrap();
-        let player = ecs.components.player_entity.get(&ev.0);
-        let mirituhg = ecs.components.mirituhg.get_mut(&ev.0);
+    for dead_e in &dead {
+        if !ecs.is_alive(*dead_e) {
+            continue;
+        }
+        let pos = match ecs.components.positions.get(dead_e) {
+            Some(pos) => pos,
+            None => continue,
+        };
+        let player = ecs.components.player_entity.get(dead_e);
+        let mirituhg = ecs.components.mirituhg.get_mut(dead_e);
 
         if player.is_some() {
             data.dead = true;
+            delete_save(data);
             audio::play_sound(
                 &data.audio.death2,
                 PlaySoundParams {
@@ -390,8 +445,8 @@ pub fn handle_death(data: &mut GameData, ecs: &mut Ecs, death_events: &Vec<Death
     }
 
     if let Some(pos) = spawn_death {
-        data.screen_shake.shake(2.25, 4.);
-        spawn_mirituhg_death(data, pos, ecs);
+        ecs.events.send(BossPhaseChanged(MiritughState::Dead));
+        spawn_prefab(data, ecs, "mirituhg_death", pos);
     }
 
     for (pickup, pos) in pickups {
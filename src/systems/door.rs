@@ -0,0 +1,76 @@
+use macroquad::prelude::*;
+
+use crate::{
+    entity::{
+        door::Door,
+        entities::Ecs,
+        events::{CollisionOngoing, CollisionStarted, EventReader},
+        player::PlayerData,
+    },
+    floor::RoomKind,
+    game_data::GameData,
+};
+
+use super::collision::read_contacts;
+
+/// Doors open once the room is completed. The one to the boss stays locked
+/// until the player is fully aberrated or the rest of the floor is cleared.
+pub fn update_doors(data: &GameData, ecs: &mut Ecs) {
+    let aberration = ecs
+        .query_ref::<&PlayerData>()
+        .next()
+        .map_or(0., |player_data| player_data.aberration);
+    let boss_unlocked = aberration >= 1. || data.floor.rest_cleared();
+
+    for door in ecs.query::<&mut Door>() {
+        let to_boss = data.floor.rooms[door.room].kind == RoomKind::Boss;
+        door.locked = !data.current_room.completed || to_boss && !boss_unlocked;
+    }
+}
+
+/// Takes the player to the next room when they walk into an unlocked door.
+pub fn use_doors(
+    data: &mut GameData,
+    ecs: &mut Ecs,
+    started_reader: &mut EventReader<CollisionStarted>,
+    ongoing_reader: &mut EventReader<CollisionOngoing>,
+) {
+    let contacts = read_contacts(ecs, started_reader, ongoing_reader);
+    if data.map_change_requested {
+        return;
+    }
+
+    for (a, b) in contacts {
+        for (door_e, other_e) in [(a, b), (b, a)] {
+            if !ecs.components.player_data.contains(&other_e) {
+                continue;
+            }
+            let Some(door) = ecs.components.doors.get(&door_e) else {
+                continue;
+            };
+            if !door.locked {
+                let (room, entrance) = (door.room, door.direction.opposite());
+                data.enter_room(ecs, room, Some(entrance));
+                return;
+            }
+        }
+    }
+}
+
+pub fn draw_doors(ecs: &Ecs) {
+    for (door, pos) in ecs.query_ref::<(&Door, &Vec2)>() {
+        let half_size = door.half_size();
+        let color = if door.locked {
+            Color::from_rgba(122, 36, 61, 255)
+        } else {
+            Color::from_rgba(232, 193, 112, 160)
+        };
+        draw_rectangle(
+            pos.x - half_size.x,
+            pos.y - half_size.y,
+            half_size.x * 2.,
+            half_size.y * 2.,
+            color,
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use std::f32::consts::{PI, TAU};
+use std::{
+    collections::HashMap,
+    f32::consts::{PI, TAU},
+};
 
 use macroquad::{
     audio::{self, PlaySoundParams},
@@ -8,44 +11,53 @@ use macroquad::{
 
 use crate::{
     entity::{
-        entities::Ecs, events::DamageEvent, hopper::spawn_hopper, mirituhg::MiritughState,
-        projectile::spawn_bullet, tags::EntityType,
+        animated_sprite::AnimatedSprite,
+        entities::Ecs,
+        entity_id::Entity,
+        events::{BossPhaseChanged, DamageEvent, EventReader, RoomCleared},
+        hierarchy::Muzzle,
+        hopper::Hopper,
+        mirituhg::{MiritughState, Mirituhg, MirituhgDeath},
+        player::PlayerData,
+        prefab::spawn_prefab,
+        projectile::spawn_bullet,
+        spitter::Spitter,
+        stomper::Stomper,
+        tags::{EntityType, Velocity},
     },
     game_data::GameData,
     rand_utils::rand_dir,
+    save::delete_save,
 };
 
-use super::collision::ColliderType;
-
-pub fn update_enemies(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Vec<DamageEvent>) {
-    let hoppers = ecs.check_components(|e, comps| {
-        comps.hoppers.contains_key(e)
-            && comps.positions.contains_key(e)
-            && comps.velocities.contains_key(e)
-            && comps.colliders.contains_key(e)
-            && comps.animated_sprites.contains_key(e)
-    });
-
-    let players = ecs.check_components(|e, comps| {
-        comps.player_data.contains_key(e) && comps.positions.contains_key(e)
-    });
-
-    let player_pos = {
-        let mut pos = Vec2::ZERO;
-        for player_e in &players {
-            pos = *ecs.components.positions.get(player_e).unwrap();
-            break;
-        }
-        pos
-    };
-
-    for hopper_e in &hoppers {
-        let hopper = ecs.components.hoppers.get_mut(hopper_e).unwrap();
-        let position = ecs.components.positions.get(hopper_e).unwrap();
-        let velocity = ecs.components.velocities.get_mut(hopper_e).unwrap();
-        let sprite = ecs.components.animated_sprites.get_mut(hopper_e).unwrap();
+use super::{
+    collision::{Collider, ColliderType},
+    steering::Crowd,
+};
 
-        hopper.jump_timer.update();
+pub fn update_enemies(data: &mut GameData, ecs: &mut Ecs) {
+    let dt = data.time.dt;
+    let players = ecs
+        .query_ref::<(Entity, &PlayerData, &Vec2)>()
+        .map(|(e, _, pos)| (e, *pos))
+        .collect::<Vec<_>>();
+
+    let player_pos = players.first().map_or(Vec2::ZERO, |(_, pos)| *pos);
+
+    let mut damage_events = vec![];
+
+    // Ground enemies follow a shared flow field and keep apart from each other
+    let crowd = Crowd::new(ecs);
+    let map = &data.maps[data.current_room.map_index];
+    for (hopper_e, hopper, position, velocity, collider, sprite) in ecs.query::<(
+        Entity,
+        &mut Hopper,
+        &Vec2,
+        &mut Velocity,
+        &Collider,
+        &mut AnimatedSprite,
+    )>() {
+        hopper.jump_timer.update(dt);
 
         if hopper.jump_timer.just_completed() {
             hopper.jumping = !hopper.jumping;
@@ -61,44 +73,48 @@ pub fn update_enemies(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Ve
             }
         }
         let vel = if hopper.jumping {
-            if velocity.length_squared() > 0. {
-                velocity.normalize() * hopper.jump_move_speed
+            if velocity.0.length_squared() > 0. {
+                velocity.0.normalize() * hopper.jump_move_speed
             } else {
                 Vec2::ZERO
             }
         } else {
-            (player_pos - *position).normalize() * hopper.move_speed
+            let radius = collider.shape.bounding_radius();
+            let direction = data
+                .navigation
+                .flow_direction(map, *position, player_pos, radius);
+            crowd.steer(hopper_e, direction) * hopper.move_speed
         };
 
         if !vel.is_nan() {
-            *velocity = vel;
+            velocity.0 = vel;
         }
     }
 
-    let spitters = ecs.check_components(|e, comps| {
-        comps.spitters.contains_key(e)
-            && comps.positions.contains_key(e)
-            && comps.colliders.contains_key(e)
-            && comps.animated_sprites.contains_key(e)
-    });
+    let muzzles = ecs
+        .query_ref::<(Entity, &Muzzle)>()
+        .filter_map(|(e, muzzle)| Some((e, *ecs.components.positions.get(&muzzle.0)?)))
+        .collect::<HashMap<_, _>>();
 
     let mut bullets = vec![];
-    for spitter_e in &spitters {
-        let spitter = ecs.components.spitters.get_mut(spitter_e).unwrap();
-        let position = ecs.components.positions.get(spitter_e).unwrap();
-        let sprite = ecs.components.animated_sprites.get_mut(spitter_e).unwrap();
-
-        spitter.attack_timer.update();
-        spitter.spit_timer.update();
-
-        if spitter.attack_timer.just_completed() {
+    for (spitter_e, spitter, position, _, sprite) in
+        ecs.query::<(Entity, &mut Spitter, &Vec2, &Collider, &mut AnimatedSprite)>()
+    {
+        spitter.attack_timer.update(dt);
+        spitter.spit_timer.update(dt);
+
+        // Waits with the next attack until the player is in sight
+        let mouth_pos = muzzles.get(&spitter_e).copied().unwrap_or(*position);
+        if spitter.attack_timer.completed()
+            && data.current_map().line_of_sight(mouth_pos, player_pos)
+        {
             sprite.set_animation("spit");
             spitter.attack_timer.reset();
             spitter.spit_timer.reset();
         }
         if spitter.spit_timer.just_completed() {
             let bullet_velocity = (player_pos - *position).normalize() * 50.;
-            bullets.push((*position + vec2(0., -5.), bullet_velocity));
+            bullets.push((mouth_pos, bullet_velocity));
             audio::play_sound(
                 &data.audio.shoot,
                 PlaySoundParams {
@@ -126,22 +142,16 @@ pub fn update_enemies(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Ve
         );
     }
 
-    let stompers = ecs.check_components(|e, comps| {
-        comps.stompers.contains_key(e)
-            && comps.positions.contains_key(e)
-            && comps.velocities.contains_key(e)
-            && comps.colliders.contains_key(e)
-            && comps.animated_sprites.contains_key(e)
-    });
-
-    for stomper_e in &stompers {
-        let stomper = ecs.components.stompers.get_mut(stomper_e).unwrap();
-        let position = ecs.components.positions.get(stomper_e).unwrap();
-        let sprite = ecs.components.animated_sprites.get_mut(stomper_e).unwrap();
-        let velocity = ecs.components.velocities.get_mut(stomper_e).unwrap();
-
-        stomper.damage_timer.update();
-        stomper.jump_timer.update();
+    for (stomper_e, stomper, position, velocity, collider, sprite) in ecs.query::<(
+        Entity,
+        &mut Stomper,
+        &Vec2,
+        &mut Velocity,
+        &Collider,
+        &mut AnimatedSprite,
+    )>() {
+        stomper.damage_timer.update(dt);
+        stomper.jump_timer.update(dt);
 
         let dist_to_player = (player_pos - *position).length();
         // TODO: check range to player to start
@@ -153,14 +163,16 @@ pub fn update_enemies(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Ve
         if stomper.jumping {
             let vel = (player_pos - *position).normalize() * stomper.jump_move_speed;
             if !vel.is_nan() {
-                *velocity = vel;
+                velocity.0 = vel;
             }
             if stomper.damage_timer.just_completed() && dist_to_player < 26. {
-                for player_e in &players {
+                data.time.hit_stop(0.08);
+                for (player_e, _) in &players {
                     damage_events.push(DamageEvent {
-                        source: *stomper_e,
+                        source: stomper_e,
                         target: *player_e,
                         damage: 1.,
+                        knockback: 0.,
                     });
                 }
             }
@@ -171,31 +183,33 @@ pub fn update_enemies(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Ve
                 stomper.jump_timer.reset();
             }
         } else {
-            let vel = (player_pos - *position).normalize() * stomper.move_speed;
+            let map = &data.maps[data.current_room.map_index];
+            let radius = collider.shape.bounding_radius();
+            let direction = data
+                .navigation
+                .flow_direction(map, *position, player_pos, radius);
+            let vel = crowd.steer(stomper_e, direction) * stomper.move_speed;
             if !vel.is_nan() {
-                *velocity = vel;
+                velocity.0 = vel;
             }
         }
     }
 
-    let mirituhgs = ecs.check_components(|e, comps| {
-        comps.mirituhg.contains_key(e)
-            && comps.positions.contains_key(e)
-            && comps.velocities.contains_key(e)
-            && comps.colliders.contains_key(e)
-            && comps.animated_sprites.contains_key(e)
-    });
+    ecs.events.damage.extend(damage_events);
 
     let mut hopper_spawns = vec![];
     let mut bullets = vec![];
-
-    for mirituhg_e in &mirituhgs {
-        let mirituhg = ecs.components.mirituhg.get_mut(mirituhg_e).unwrap();
-        let position = ecs.components.positions.get(mirituhg_e).unwrap();
-        let sprite = ecs.components.animated_sprites.get_mut(mirituhg_e).unwrap();
-        let velocity = ecs.components.velocities.get_mut(mirituhg_e).unwrap();
-
-        mirituhg.update();
+    let mut phase_changes = vec![];
+
+    for (mirituhg, position, velocity, collider, sprite) in ecs.query::<(
+        &mut Mirituhg,
+        &Vec2,
+        &mut Velocity,
+        &Collider,
+        &mut AnimatedSprite,
+    )>() {
+        mirituhg.update(dt);
+        let previous_state = mirituhg.state;
 
         let dist = mirituhg.target_pos - *position;
         if mirituhg.state == MiritughState::Idle {
@@ -284,21 +298,28 @@ pub fn update_enemies(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Ve
         }
 
         if dist.length_squared() > 0. {
-            *velocity = (mirituhg.target_pos - *position).normalize() * mirituhg.move_speed;
+            let map = &data.maps[data.current_room.map_index];
+            let radius = collider.shape.bounding_radius();
+            velocity.0 = data
+                .navigation
+                .direction(map, *position, mirituhg.target_pos, radius)
+                * mirituhg.move_speed;
+        }
+
+        if mirituhg.state != previous_state {
+            phase_changes.push(BossPhaseChanged(mirituhg.state));
         }
     }
 
-    let mirituhgs_deaths = ecs.check_components(|e, comps| {
-        comps.mirituhg_death.contains_key(e) && comps.animated_sprites.contains_key(e)
-    });
+    ecs.events.boss_phase_changed.extend(phase_changes);
 
-    for mirituhg_e in &mirituhgs_deaths {
-        let sprite = ecs.components.animated_sprites.get(mirituhg_e).unwrap();
+    for (_, sprite) in ecs.query_ref::<(&MirituhgDeath, &AnimatedSprite)>() {
         if sprite.current_animation == "death".to_string() && sprite.current_animation().1.completed
         {
             data.end_game_screen.show();
             data.paused = true;
             data.game_completed = true;
+            delete_save(data);
         }
     }
 
@@ -315,7 +336,41 @@ pub fn update_enemies(data: &mut GameData, ecs: &mut Ecs, damage_events: &mut Ve
     }
 
     for _ in hopper_spawns {
-        spawn_hopper(data, vec2(180., 120.), ecs);
-        spawn_hopper(data, vec2(180., 120.), ecs);
+        spawn_prefab(data, ecs, "hopper", vec2(180., 120.));
+        spawn_prefab(data, ecs, "hopper", vec2(180., 120.));
+    }
+}
+
+/// Slows the game down for a moment when a room is cleared, and for longer
+/// when the Mirituhg dies.
+pub fn slow_motion_on_clear(
+    data: &mut GameData,
+    ecs: &Ecs,
+    room_cleared_reader: &mut EventReader<RoomCleared>,
+    boss_phase_reader: &mut EventReader<BossPhaseChanged>,
+) {
+    if room_cleared_reader
+        .read(ecs.events.get::<RoomCleared>())
+        .next()
+        .is_some()
+    {
+        data.time.slow_motion(0.2, 0.8);
+    }
+    for BossPhaseChanged(phase) in boss_phase_reader.read(ecs.events.get::<BossPhaseChanged>()) {
+        if *phase == MiritughState::Dead {
+            data.time.slow_motion(0.1, 2.);
+        }
+    }
+}
+
+pub fn shake_on_boss_phase(
+    data: &mut GameData,
+    ecs: &Ecs,
+    reader: &mut EventReader<BossPhaseChanged>,
+) {
+    for BossPhaseChanged(phase) in reader.read(ecs.events.get::<BossPhaseChanged>()) {
+        if *phase == MiritughState::Dead {
+            data.screen_shake.shake(2.25, 4.);
+        }
     }
 }
@@ -0,0 +1,41 @@
+use macroquad::math::Vec2;
+
+use crate::entity::{
+    entities::Ecs,
+    entity_id::Entity,
+    hierarchy::{LocalTransform, Parent},
+};
+
+/// Moves attached entities to their parent's position plus their local
+/// offset. Runs after movement so children don't trail a frame behind.
+pub fn propagate_transforms(ecs: &mut Ecs) {
+    let children = ecs
+        .query_ref::<(Entity, &Parent, &LocalTransform)>()
+        .map(|(e, _, _)| e)
+        .collect::<Vec<_>>();
+
+    let mut positions = vec![];
+    for child in children {
+        match world_position(ecs, child) {
+            Some(position) => positions.push((child, position)),
+            // The parent is gone but the child wasn't destroyed with it.
+            None => ecs.despawn(child),
+        }
+    }
+
+    for (child, position) in positions {
+        ecs.components.positions.insert(child, position);
+    }
+}
+
+fn world_position(ecs: &Ecs, entity: Entity) -> Option<Vec2> {
+    if !ecs.is_alive(entity) {
+        return None;
+    }
+    let parent = ecs.components.parents.get(&entity);
+    let local = ecs.components.local_transforms.get(&entity);
+    match (parent, local) {
+        (Some(parent), Some(local)) => Some(world_position(ecs, parent.0)? + local.translation),
+        _ => ecs.components.positions.get(&entity).copied(),
+    }
+}
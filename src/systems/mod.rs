@@ -1,9 +1,16 @@
 pub mod collision;
 pub mod damageable;
+pub mod door;
 pub mod enemy;
+pub mod hierarchy;
 pub mod movement;
+pub mod pickup;
 pub mod player;
+pub mod schedule;
 pub mod spawn;
 pub mod sprite;
+pub mod steering;
+pub mod tiles;
 pub mod timer;
+pub mod trigger;
 pub mod weapon;
@@ -3,50 +3,96 @@ use std::collections::HashMap;
 use macroquad::prelude::*;
 
 use crate::{
-    entity::{entities::Ecs, entity_id::Entity},
+    entity::{
+        animated_sprite::AnimatedSprite,
+        entities::Ecs,
+        entity_id::Entity,
+        events::{CollisionEnded, CollisionOngoing, CollisionStarted},
+        tags::{PhysicsBody, PreviousPosition, Velocity},
+    },
     game_data::GameData,
-    physics::collision::{resolve_circle_collision, resolve_map_collision, Collision},
+    physics::{
+        collision::{resolve_collision, resolve_map_collision, sweep_collider, Collision},
+        spatial_hash::SpatialHash,
+    },
+    systems::collision::Collider,
 };
 
-pub fn move_entities(data: &mut GameData, ecs: &mut Ecs) -> HashMap<(Entity, Entity), Collision> {
-    let moveables = ecs.check_components(|e, comps| {
-        comps.positions.contains_key(e) && comps.velocities.contains_key(e)
-    });
-    let comps = &mut ecs.components;
-
-    let mut colliders = vec![];
-    for moveable_e in &moveables {
-        if let Some(_) = comps.colliders.get(moveable_e) {
-            let other_pos = *comps.positions.get(moveable_e).unwrap();
-            let other_coll = comps.colliders.get(moveable_e).unwrap();
-            colliders.push((*moveable_e, other_pos, other_coll));
-        }
+/// Remembers where sprites are before the step moves them, so drawing can
+/// interpolate between steps.
+pub fn store_previous_positions(ecs: &mut Ecs) {
+    let positions = ecs
+        .query_ref::<(Entity, &Vec2, &AnimatedSprite)>()
+        .map(|(e, pos, _)| (e, *pos))
+        .collect::<Vec<_>>();
+
+    for (entity, position) in positions {
+        ecs.components
+            .previous_positions
+            .insert(entity, PreviousPosition(position));
+    }
+}
+
+/// Turns the impulses of the last step into knockback and slows the
+/// knockback down by the body's friction.
+pub fn integrate_bodies(ecs: &mut Ecs, dt: f32) {
+    for body in ecs.query::<&mut PhysicsBody>() {
+        body.knockback += body.impulse / body.mass;
+        body.impulse = Vec2::ZERO;
+        body.knockback *= (1. - body.friction * dt).max(0.);
     }
+}
+
+pub fn move_entities(data: &mut GameData, ecs: &mut Ecs, dt: f32) {
+    let colliders = ecs
+        .query_ref::<(Entity, &Vec2, &Velocity, &Collider)>()
+        .map(|(e, pos, _, coll)| (e, *pos, coll.clone()))
+        .collect::<Vec<_>>();
+    let collider_indices = colliders
+        .iter()
+        .enumerate()
+        .map(|(i, (e, _, _))| (*e, i))
+        .collect::<HashMap<_, _>>();
+    let spatial_hash = SpatialHash::new(&colliders);
 
     let mut failed_entities = vec![];
 
     let mut collisions = HashMap::<(Entity, Entity), Collision>::new();
-    for moveable_e in &moveables {
-        let position = comps.positions.get_mut(moveable_e).unwrap();
-        let velocity = comps.velocities.get(moveable_e).unwrap();
-        let collider = comps.colliders.get(moveable_e);
-
-        let mut desired_pos = *position + *velocity * get_frame_time();
+    for (moveable_e, position, velocity, collider, body) in ecs.query::<(
+        Entity,
+        &mut Vec2,
+        &Velocity,
+        Option<&Collider>,
+        Option<&PhysicsBody>,
+    )>() {
+        let knockback = body.map_or(Vec2::ZERO, |body| body.knockback);
+        let mut desired_pos = *position + (velocity.0 + knockback) * dt;
         if desired_pos.x.is_nan() || desired_pos.y.is_nan() {
-            failed_entities.push(*moveable_e);
-            println!("WTF {:?} {:?} {:?}", *position, velocity, get_frame_time());
+            failed_entities.push(moveable_e);
             continue;
         }
 
         if let Some(collider) = collider {
+            let index = collider_indices[&moveable_e];
+            let (pos, swept_collisions) = sweep_collider(
+                index,
+                *position,
+                desired_pos,
+                &colliders,
+                &spatial_hash,
+                data.current_map(),
+            );
+            desired_pos = pos;
             let (pos, new_collisions) =
-                resolve_circle_collision(*moveable_e, desired_pos, &colliders);
-            collisions.extend(new_collisions);
+                resolve_collision(index, desired_pos, &colliders, &spatial_hash);
+            record_collisions(&mut collisions, new_collisions);
             desired_pos = pos;
             let (pos, new_collisions) =
-                resolve_map_collision(*moveable_e, data, data.current_map(), desired_pos, collider);
-            collisions.extend(new_collisions);
+                resolve_map_collision(moveable_e, data.current_map(), desired_pos, collider);
+            record_collisions(&mut collisions, new_collisions);
             desired_pos = pos;
+            // Swept contacts carry the earlier time of impact
+            record_collisions(&mut collisions, swept_collisions);
         }
         *position = desired_pos;
     }
@@ -56,5 +102,42 @@ pub fn move_entities(data: &mut GameData, ecs: &mut Ecs) -> HashMap<(Entity, Ent
         ecs.despawn(failed_e);
     }
 
-    collisions
+    send_collision_events(ecs, &collisions);
+    ecs.collisions = collisions;
+    ecs.spatial_hash = spatial_hash;
+}
+
+/// Adds the contacts of one collider, skipping pairs already recorded the
+/// other way around, e.g. when both colliders swept into each other.
+fn record_collisions(
+    collisions: &mut HashMap<(Entity, Entity), Collision>,
+    new_collisions: HashMap<(Entity, Entity), Collision>,
+) {
+    for ((a, b), collision) in new_collisions {
+        if !collisions.contains_key(&(b, a)) {
+            collisions.insert((a, b), collision);
+        }
+    }
+}
+
+/// Compares the collisions of this step with the ones of the last step, so
+/// systems can react to contacts starting and ending. A pair counts as the
+/// same contact in either order.
+fn send_collision_events(ecs: &mut Ecs, collisions: &HashMap<(Entity, Entity), Collision>) {
+    let touching = |map: &HashMap<(Entity, Entity), Collision>, (a, b): (Entity, Entity)| {
+        map.contains_key(&(a, b)) || map.contains_key(&(b, a))
+    };
+
+    for (a, b) in collisions.keys().copied() {
+        if touching(&ecs.collisions, (a, b)) {
+            ecs.events.send(CollisionOngoing(a, b));
+        } else {
+            ecs.events.send(CollisionStarted(a, b));
+        }
+    }
+    for (a, b) in ecs.collisions.keys().copied() {
+        if !touching(collisions, (a, b)) {
+            ecs.events.send(CollisionEnded(a, b));
+        }
+    }
 }
@@ -0,0 +1,51 @@
+use macroquad::audio::{self, PlaySoundParams};
+
+use crate::{
+    entity::{
+        entities::Ecs,
+        events::{EventReader, PickupCollected},
+        pickup::Pickup,
+    },
+    game_data::GameData,
+};
+
+pub fn apply_pickups(ecs: &mut Ecs, reader: &mut EventReader<PickupCollected>) {
+    let collected = reader
+        .read(ecs.events.get::<PickupCollected>())
+        .map(|e| (e.collector, e.pickup))
+        .collect::<Vec<_>>();
+
+    for (collector, pickup) in collected {
+        let player = match ecs.components.player_data.get_mut(&collector) {
+            Some(player) => player,
+            None => continue,
+        };
+        let up_data = player.get_upgraded_data();
+
+        match pickup {
+            Pickup::Health(increase) => {
+                if let Some(health) = ecs.components.health.get_mut(&collector) {
+                    health.hp = (health.hp + increase).min(up_data.max_hp as f32);
+                }
+            }
+            Pickup::AnomalyBig => {
+                player.aberration = (player.aberration - 0.1).max(0.);
+            }
+            Pickup::AnomalySmall => {
+                player.aberration = (player.aberration - 0.02).max(0.);
+            }
+        }
+    }
+}
+
+pub fn play_pickup_sounds(data: &GameData, ecs: &Ecs, reader: &mut EventReader<PickupCollected>) {
+    for _ in reader.read(ecs.events.get::<PickupCollected>()) {
+        audio::play_sound(
+            &data.audio.confirm2,
+            PlaySoundParams {
+                volume: data.settings.sfx_volume * 1.2,
+                ..Default::default()
+            },
+        );
+    }
+}
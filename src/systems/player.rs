@@ -1,23 +1,21 @@
 use macroquad::prelude::*;
 
 use crate::{
-    entity::entities::Ecs, game_data::GameData, input_manager::Action, items::weapon::Weapon,
+    entity::{entities::Ecs, player::PlayerData, tags::Velocity},
+    game_data::GameData,
+    input_manager::Action,
+    items::weapon::Weapon,
+    systems::collision::Collider,
 };
 
 pub fn update_player(data: &mut GameData, ecs: &mut Ecs) {
-    let players = ecs.check_components(|e, comps| {
-        comps.player_data.contains_key(e)
-            && comps.positions.contains_key(e)
-            && comps.colliders.contains_key(e)
-            && comps.velocities.contains_key(e)
-    });
-
-    for player in &players {
-        let player_data = ecs.components.player_data.get_mut(player).unwrap();
-        let velocity = ecs.components.velocities.get_mut(player).unwrap();
+    let dt = data.time.dt;
+    for (player_data, _, _, velocity) in
+        ecs.query::<(&mut PlayerData, &Vec2, &Collider, &mut Velocity)>()
+    {
         let up_data = player_data.get_upgraded_data();
 
-        player_data.aberration_increase_timer.update();
+        player_data.aberration_increase_timer.update(dt);
         // if !data.current_room.completed
         //     && !data.current_room.aberration_completed
         //     && player_data.aberration_increase_timer.just_completed()
@@ -31,7 +29,7 @@ pub fn update_player(data: &mut GameData, ecs: &mut Ecs) {
 
         if let Weapon::Dash(ref dash) = data.weapon {
             if dash.dashing {
-                *velocity = dash.direction * dash.speed;
+                velocity.0 = dash.direction * dash.speed;
                 return;
             }
         }
@@ -57,9 +55,9 @@ pub fn update_player(data: &mut GameData, ecs: &mut Ecs) {
         }
 
         if dir.length_squared() > 0. {
-            *velocity = up_data.move_speed * dir.normalize();
+            velocity.0 = up_data.move_speed * dir.normalize();
         } else {
-            *velocity = Vec2::ZERO;
+            velocity.0 = Vec2::ZERO;
         };
     }
 }
@@ -0,0 +1,435 @@
+use macroquad::prelude::*;
+
+use crate::{
+    entity::{
+        events::{EventReader, RoomCleared},
+        player::PlayerData,
+        prefab::spawn_prefab,
+    },
+    game_data::GameData,
+    game_state::GameState,
+    input_manager::Action,
+    map::map::Map,
+    physics::spatial_hash::draw_spatial_hash,
+    save::save_run,
+    schedule::{Schedule, Stage, System},
+    settings::WindowSize,
+    ui::upgrade_screen::apply_upgrades,
+};
+
+#[cfg(debug_assertions)]
+use crate::map::hot_reload::MapWatcher;
+
+use super::{
+    collision::draw_colliders,
+    damageable::{
+        apply_damage, damage_on_collision, despawn_on_collision, flash_on_damage, handle_death,
+        kill_entities, update_damageables,
+    },
+    door::{draw_doors, update_doors, use_doors},
+    enemy::{shake_on_boss_phase, slow_motion_on_clear, update_enemies},
+    hierarchy::propagate_transforms,
+    movement::{integrate_bodies, move_entities, store_previous_positions},
+    pickup::{apply_pickups, play_pickup_sounds},
+    player::update_player,
+    spawn::spawn_creatures,
+    sprite::{draw_animated_sprites, update_animated_sprites},
+    tiles::apply_tile_effects,
+    timer::update_timers,
+    trigger::run_triggers,
+    weapon::update_weapon,
+};
+
+pub fn not_paused(data: &GameData) -> bool {
+    !data.paused
+}
+
+/// Regular gameplay systems only run while playing and not paused.
+fn gameplay(system: System) -> System {
+    system.in_state(GameState::Playing).run_if(not_paused)
+}
+
+/// `maps` are only read for the map hot reload of debug builds.
+pub fn add_game_systems(schedule: &mut Schedule, maps: &[Map]) {
+    let mut fullscreen = false;
+    let mut upgrade_reader = EventReader::default();
+
+    schedule
+        .add(System::new("input", Stage::Input, |data, ecs| {
+            data.input.update(ecs, &data.camera);
+        }))
+        .add(
+            System::new("window_keys", Stage::Input, move |data, _| {
+                if (is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt))
+                    && is_key_pressed(KeyCode::Enter)
+                {
+                    fullscreen = !fullscreen;
+
+                    if fullscreen {
+                        data.settings.set_window_size(WindowSize::Fullscreen);
+                    } else {
+                        data.settings.set_window_size(WindowSize::default());
+                    }
+                }
+
+                if is_key_pressed(KeyCode::F1) {
+                    data.debug_collisions = !data.debug_collisions;
+                }
+
+                if data.input.is_just_pressed(Action::Pause) {
+                    if data.paused {
+                        data.paused = false;
+                        data.show_pause_menu = false;
+                        data.show_floor_map = false;
+                    } else {
+                        data.paused = true;
+                        data.show_pause_menu = true;
+                    }
+                }
+            })
+            .after("input"),
+        );
+
+    schedule
+        .add(
+            System::new("debug_keys", Stage::PreUpdate, |data, ecs| {
+                if is_key_pressed(KeyCode::F5) {
+                    spawn_prefab(data, ecs, "mirituhg", vec2(180., 120.));
+                }
+                if is_key_pressed(KeyCode::F6) && !data.map_change_requested {
+                    let neighbour = data.floor.current().doors.iter().next();
+                    if let Some((dir, room)) = neighbour.map(|(dir, room)| (*dir, *room)) {
+                        data.enter_room(ecs, room, Some(dir.opposite()));
+                    }
+                }
+                if is_key_pressed(KeyCode::F7) {
+                    data.debug_spatial_hash = !data.debug_spatial_hash;
+                }
+            })
+            .in_state(GameState::Playing),
+        )
+        .add(
+            System::new("map_transition", Stage::PreUpdate, |data, ecs| {
+                if data.map_change_requested && data.screen_dimmer.just_dimmed {
+                    if let Some(next_room) = data.next_room.take() {
+                        data.map_change_requested = false;
+                        data.current_room.despawn(ecs);
+                        data.current_room = next_room;
+                        data.floor.enter(data.current_room.floor_index);
+                        data.navigation.clear();
+                        let new_player_pos = data.spawn_map_entities(ecs);
+                        data.current_room.started = true;
+                        for (_, pos) in ecs.query::<(&PlayerData, &mut Vec2)>() {
+                            *pos = new_player_pos;
+                        }
+                    }
+                }
+            })
+            .in_state(GameState::Playing)
+            .after("debug_keys"),
+        )
+        .add(
+            System::new("apply_upgrades", Stage::PreUpdate, move |data, ecs| {
+                apply_upgrades(data, ecs, &mut upgrade_reader)
+            })
+            .in_state(GameState::Playing),
+        );
+
+    #[cfg(debug_assertions)]
+    {
+        let mut map_watcher = MapWatcher::new(maps);
+        schedule.add(
+            System::new("hot_reload_map", Stage::PreUpdate, move |data, _| {
+                map_watcher.update(data)
+            })
+            .in_state(GameState::Playing)
+            .before("map_transition"),
+        );
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = maps;
+
+    let mut death_reader = EventReader::default();
+    let mut pickup_reader = EventReader::default();
+    let mut pickup_sound_reader = EventReader::default();
+    let mut boss_phase_reader = EventReader::default();
+    let mut slow_motion_room_reader = EventReader::default();
+    let mut slow_motion_boss_reader = EventReader::default();
+    let mut damage_reader = EventReader::default();
+    let mut damage_started_reader = EventReader::default();
+    let mut damage_ongoing_reader = EventReader::default();
+    let mut despawn_started_reader = EventReader::default();
+    let mut despawn_ongoing_reader = EventReader::default();
+    let mut trigger_started_reader = EventReader::default();
+    let mut trigger_ended_reader = EventReader::default();
+    let mut door_started_reader = EventReader::default();
+    let mut door_ongoing_reader = EventReader::default();
+
+    schedule
+        .add(
+            System::new("store_previous_positions", Stage::Update, |_, ecs| {
+                store_previous_positions(ecs)
+            })
+            .in_state(GameState::Playing),
+        )
+        .add(gameplay(System::new(
+            "update_timers",
+            Stage::Update,
+            |data, ecs| update_timers(ecs, data.time.dt),
+        )))
+        .add(gameplay(System::new(
+            "update_damageables",
+            Stage::Update,
+            |data, ecs| update_damageables(ecs, data.time.dt),
+        )))
+        .add(gameplay(System::new(
+            "damage_on_collision",
+            Stage::Update,
+            move |_, ecs| {
+                damage_on_collision(ecs, &mut damage_started_reader, &mut damage_ongoing_reader)
+            },
+        )))
+        .add(gameplay(System::new(
+            "despawn_on_collision",
+            Stage::Update,
+            move |data, ecs| {
+                despawn_on_collision(
+                    data,
+                    ecs,
+                    &mut despawn_started_reader,
+                    &mut despawn_ongoing_reader,
+                )
+            },
+        )))
+        .add(gameplay(System::new(
+            "run_triggers",
+            Stage::Update,
+            move |data, ecs| {
+                run_triggers(
+                    data,
+                    ecs,
+                    &mut trigger_started_reader,
+                    &mut trigger_ended_reader,
+                )
+            },
+        )))
+        .add(gameplay(System::new(
+            "use_doors",
+            Stage::Update,
+            move |data, ecs| {
+                use_doors(
+                    data,
+                    ecs,
+                    &mut door_started_reader,
+                    &mut door_ongoing_reader,
+                )
+            },
+        )))
+        .add(gameplay(
+            System::new("apply_pickups", Stage::Update, move |_, ecs| {
+                apply_pickups(ecs, &mut pickup_reader)
+            })
+            .after("despawn_on_collision"),
+        ))
+        .add(gameplay(
+            System::new("play_pickup_sounds", Stage::Update, move |data, ecs| {
+                play_pickup_sounds(data, ecs, &mut pickup_sound_reader)
+            })
+            .after("despawn_on_collision"),
+        ))
+        .add(gameplay(System::new(
+            "kill_entities",
+            Stage::Update,
+            kill_entities,
+        )))
+        .add(gameplay(
+            System::new("handle_death", Stage::Update, move |data, ecs| {
+                handle_death(data, ecs, &mut death_reader)
+            })
+            .after("kill_entities"),
+        ))
+        .add(gameplay(System::new(
+            "update_player",
+            Stage::Update,
+            update_player,
+        )))
+        .add(gameplay(System::new(
+            "update_weapon",
+            Stage::Update,
+            |data, ecs| update_weapon(ecs, data),
+        )))
+        .add(gameplay(
+            System::new("spawn_creatures", Stage::Update, spawn_creatures).before("update_enemies"),
+        ))
+        .add(gameplay(System::new(
+            "update_enemies",
+            Stage::Update,
+            update_enemies,
+        )))
+        .add(gameplay(
+            System::new("shake_on_boss_phase", Stage::Update, move |data, ecs| {
+                shake_on_boss_phase(data, ecs, &mut boss_phase_reader)
+            })
+            .after("handle_death")
+            .after("update_enemies"),
+        ))
+        .add(gameplay(
+            System::new("slow_motion_on_clear", Stage::Update, move |data, ecs| {
+                slow_motion_on_clear(
+                    data,
+                    ecs,
+                    &mut slow_motion_room_reader,
+                    &mut slow_motion_boss_reader,
+                )
+            })
+            .after("handle_death")
+            .after("update_enemies"),
+        ))
+        .add(gameplay(
+            System::new("apply_tile_effects", Stage::Update, apply_tile_effects)
+                .after("update_player")
+                .after("update_enemies")
+                .before("apply_damage"),
+        ))
+        .add(gameplay(
+            System::new("apply_damage", Stage::Update, move |data, ecs| {
+                apply_damage(data, ecs, &mut damage_reader)
+            })
+            .after("damage_on_collision")
+            .after("update_enemies"),
+        ))
+        .add(gameplay(System::new(
+            "update_animated_sprites",
+            Stage::Update,
+            |data, ecs| update_animated_sprites(ecs, data.time.dt),
+        )));
+
+    schedule
+        .add(gameplay(
+            System::new("integrate_bodies", Stage::Physics, |data, ecs| {
+                integrate_bodies(ecs, data.time.dt)
+            })
+            .before("move_entities"),
+        ))
+        .add(gameplay(System::new(
+            "move_entities",
+            Stage::Physics,
+            |data, ecs| {
+                let dt = data.time.dt;
+                move_entities(data, ecs, dt)
+            },
+        )))
+        .add(gameplay(
+            System::new("propagate_transforms", Stage::Physics, |_, ecs| {
+                propagate_transforms(ecs)
+            })
+            .after("move_entities"),
+        ));
+
+    let mut room_cleared_reader = EventReader::default();
+
+    schedule
+        .add(gameplay(System::new(
+            "flash_on_damage",
+            Stage::PostUpdate,
+            |_, ecs| flash_on_damage(ecs),
+        )))
+        .add(
+            System::new("check_room_completed", Stage::PostUpdate, |data, ecs| {
+                let was_completed = data.current_room.completed;
+                data.current_room.check_completed(ecs);
+                if data.current_room.completed && !was_completed {
+                    ecs.events.send(RoomCleared);
+                }
+            })
+            .in_state(GameState::Playing),
+        )
+        .add(
+            System::new("clear_floor_room", Stage::PostUpdate, move |data, ecs| {
+                let cleared = room_cleared_reader
+                    .read(ecs.events.get::<RoomCleared>())
+                    .next()
+                    .is_some();
+                if cleared && !data.map_change_requested && !data.game_completed {
+                    let current = data.floor.current;
+                    data.floor.rooms[current].cleared = true;
+                    save_run(data, ecs, data.completed_rooms, false);
+                }
+            })
+            .in_state(GameState::Playing)
+            .after("check_room_completed"),
+        )
+        .add(
+            System::new("update_doors", Stage::PostUpdate, |data, ecs| {
+                update_doors(data, ecs)
+            })
+            .in_state(GameState::Playing)
+            .after("clear_floor_room"),
+        );
+
+    schedule
+        .add(
+            System::new("draw_map_base", Stage::Render, |data, _| {
+                data.current_map().draw_base();
+            })
+            .in_state(GameState::Playing),
+        )
+        .add(
+            System::new("draw_doors", Stage::Render, |_, ecs| draw_doors(ecs))
+                .in_state(GameState::Playing)
+                .after("draw_map_base"),
+        )
+        .add(
+            System::new("draw_sprites", Stage::Render, |data, ecs| {
+                draw_animated_sprites(ecs, data);
+            })
+            .in_state(GameState::Playing)
+            .after("draw_doors"),
+        )
+        .add(
+            System::new("draw_map_upper", Stage::Render, |data, _| {
+                data.current_map().draw_upper();
+            })
+            .in_state(GameState::Playing)
+            .after("draw_sprites"),
+        )
+        .add(
+            System::new("screen_dimmer", Stage::Render, |data, _| {
+                data.screen_dimmer.update(data.time.dt);
+                let dim_progress = if data.screen_dimmer.dimming {
+                    1. - data.screen_dimmer.progress()
+                } else {
+                    data.screen_dimmer.progress()
+                };
+                draw_rectangle_ex(
+                    0.,
+                    0.,
+                    360.,
+                    240.,
+                    DrawRectangleParams {
+                        color: Color::from_rgba(0, 0, 0, (dim_progress * 255.) as u8),
+                        ..Default::default()
+                    },
+                );
+            })
+            .in_state(GameState::Playing)
+            .after("draw_map_upper"),
+        )
+        .add(
+            System::new("draw_colliders", Stage::Render, |data, ecs| {
+                draw_colliders(data, ecs);
+                data.current_map().draw_colliders();
+            })
+            .in_state(GameState::Playing)
+            .run_if(|data| data.debug_collisions)
+            .after("screen_dimmer"),
+        )
+        .add(
+            System::new("draw_spatial_hash", Stage::Render, |_, ecs| {
+                draw_spatial_hash(&ecs.spatial_hash);
+            })
+            .in_state(GameState::Playing)
+            .run_if(|data| data.debug_spatial_hash)
+            .after("screen_dimmer"),
+        );
+}
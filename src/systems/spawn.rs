@@ -1,49 +1,85 @@
+use macroquad::{math::Vec2, rand, time::get_time};
+
 use crate::{
     entity::{
-        entities::Ecs, hopper::spawn_hopper, impact::spawn_dust, spitter::spawn_spitter,
-        stomper::spawn_stomper,
+        entities::Ecs,
+        impact::spawn_dust,
+        prefab::{regular_enemies, spawn_prefab},
+        spawner::Spawner,
     },
     game_data::GameData,
-    room::Enemy,
+    timer::Timer,
 };
 
-pub fn spawn_creatures(data: &mut GameData, ecs: &mut Ecs) {
-    let spawners = ecs.check_components(|e, comps| {
-        comps.positions.contains_key(e) && comps.spawners.contains_key(e)
-    });
+/// Time between the enemies of a spawner with a count.
+const SPAWN_INTERVAL: f32 = 0.4;
 
+pub fn spawn_creatures(data: &mut GameData, ecs: &mut Ecs) {
+    let room = &mut data.current_room;
+    let regular = regular_enemies(&data.prefabs)
+        .into_iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<_>>();
     let mut spawns = vec![];
-    for spawner_e in &spawners {
-        let spawner = ecs.components.spawners.get_mut(&spawner_e).unwrap();
-        let position = ecs.components.positions.get(&spawner_e).unwrap();
-        if !spawner.active {
+    for (spawner, position) in ecs.query::<(&mut Spawner, &Vec2)>() {
+        if !spawner.active || spawner.wave > room.wave {
             continue;
         }
-        if position.x.is_nan() || position.y.is_nan() {}
-        spawns.push(position.clone());
-        if data.current_room.enemies_to_spawn.len() == 0 {
-            spawner.active = false;
+        spawner.delay_timer.update(data.time.dt);
+        if !spawner.delay_timer.completed() {
+            continue;
         }
-    }
 
-    for spawn_pos in spawns {
-        if data.current_room.enemies_to_spawn.len() > 0 {
-            match data.current_room.enemies_to_spawn[data.current_room.enemies_to_spawn.len() - 1] {
-                Enemy::Hopper => {
-                    spawn_hopper(data, spawn_pos, ecs);
-                    spawn_dust(data, ecs, spawn_pos);
-                }
-                Enemy::Spitter => {
-                    spawn_spitter(data, spawn_pos, ecs);
-                    spawn_dust(data, ecs, spawn_pos);
-                }
-                Enemy::Stomper => {
-                    spawn_stomper(data, spawn_pos, ecs);
-                    spawn_dust(data, ecs, spawn_pos);
-                }
-                _ => todo!(),
+        let enemy = match &mut spawner.count {
+            Some(0) => None,
+            Some(count) => {
+                *count -= 1;
+                let choices = if spawner.enemies.is_empty() {
+                    &regular[..]
+                } else {
+                    &spawner.enemies[..]
+                };
+                spawner.delay_timer = Timer::new(SPAWN_INTERVAL, false);
+                Some(choices[rand::gen_range(0, choices.len())].clone())
+            }
+            // Every enemy of a wave's budget is allowed by one of its spawners
+            None => room
+                .enemies_to_spawn
+                .get_mut(&spawner.wave)
+                .and_then(|enemies| {
+                    let index = enemies.iter().rposition(|enemy| spawner.allows(enemy))?;
+                    Some(enemies.remove(index))
+                }),
+        };
+
+        match enemy {
+            Some(enemy) => {
+                spawner.last_spawn_time = get_time();
+                spawns.push((enemy, *position));
             }
-            data.current_room.enemies_to_spawn.pop();
+            None => spawner.active = false,
+        }
+    }
+
+    for (enemy, spawn_pos) in spawns {
+        spawn_prefab(data, ecs, &enemy, spawn_pos);
+        spawn_dust(data, ecs, spawn_pos);
+    }
+
+    // The next wave starts once the ones before are spawned and defeated
+    let room = &mut data.current_room;
+    let wave_done = ecs.components.enemies.is_empty()
+        && ecs
+            .query_ref::<&Spawner>()
+            .all(|spawner| !spawner.active || spawner.wave > room.wave);
+    if wave_done {
+        if let Some(next_wave) = ecs
+            .query_ref::<&Spawner>()
+            .filter(|spawner| spawner.active)
+            .map(|spawner| spawner.wave)
+            .min()
+        {
+            room.wave = next_wave;
         }
     }
 
@@ -1,38 +1,49 @@
 use macroquad::{
-    material::{gl_use_default_material, gl_use_material},
-    math::vec2,
+    material::{gl_use_default_material, gl_use_material, Material},
+    math::{vec2, Vec2},
 };
 use std::cmp::Ordering;
 
 use crate::{
-    entity::entities::Ecs,
+    entity::{
+        animated_sprite::AnimatedSprite,
+        entities::Ecs,
+        entity_id::Entity,
+        player::PlayerData,
+        tags::{DespawnOnAnimEnd, FlipToPlayer, LayerOffset, PreviousPosition},
+    },
     game_data::{GameData, GameMaterial},
 };
 
-pub fn update_animated_sprites(ecs: &mut Ecs) {
-    let sprites = ecs.check_components(|e, comps| comps.animated_sprites.contains_key(e));
-
-    for sprite_e in &sprites {
-        let sprite = ecs.components.animated_sprites.get_mut(&sprite_e).unwrap();
-        sprite.update();
-        if sprite.current_animation().1.completed
-            && ecs.components.despawn_on_anim_end.contains_key(sprite_e)
-        {
-            ecs.despawn(*sprite_e);
+pub fn update_animated_sprites(ecs: &mut Ecs, dt: f32) {
+    let mut finished = vec![];
+    for (sprite_e, sprite, despawn_on_anim_end) in
+        ecs.query::<(Entity, &mut AnimatedSprite, Option<&DespawnOnAnimEnd>)>()
+    {
+        sprite.update(dt);
+        if sprite.current_animation().1.completed && despawn_on_anim_end.is_some() {
+            finished.push(sprite_e);
         }
     }
+
+    for sprite_e in finished {
+        ecs.despawn(sprite_e);
+    }
 }
 
 pub fn draw_animated_sprites(ecs: &Ecs, data: &GameData) {
     let mut sprites = ecs
-        .check_components(|e, comps| {
-            comps.positions.contains_key(e) && comps.animated_sprites.contains_key(e)
-        })
-        .clone();
+        .query_ref::<(
+            &Vec2,
+            &AnimatedSprite,
+            Option<&LayerOffset>,
+            Option<&Material>,
+            Option<&FlipToPlayer>,
+            Option<&PreviousPosition>,
+        )>()
+        .collect::<Vec<_>>();
 
-    sprites.sort_by(|a, b| {
-        let a_pos = ecs.components.positions.get(&a).unwrap();
-        let b_pos = ecs.components.positions.get(&b).unwrap();
+    sprites.sort_by(|(a_pos, ..), (b_pos, ..)| {
         if a_pos.y < b_pos.y {
             Ordering::Less
         } else if a_pos.y > b_pos.y {
@@ -41,9 +52,9 @@ pub fn draw_animated_sprites(ecs: &Ecs, data: &GameData) {
             Ordering::Equal
         }
     });
-    sprites.sort_by(|a, b| {
-        let a_offset = ecs.components.layer_offset.get(&a).unwrap_or(&0);
-        let b_offset = ecs.components.layer_offset.get(&b).unwrap_or(&0);
+    sprites.sort_by(|(_, _, a_offset, ..), (_, _, b_offset, ..)| {
+        let a_offset = a_offset.map_or(0, |offset| offset.0);
+        let b_offset = b_offset.map_or(0, |offset| offset.0);
         if a_offset < b_offset {
             Ordering::Less
         } else if a_offset > b_offset {
@@ -53,29 +64,21 @@ pub fn draw_animated_sprites(ecs: &Ecs, data: &GameData) {
         }
     });
 
-    let players = ecs.check_components(|e, comps| {
-        comps.player_data.contains_key(e) && comps.positions.contains_key(e)
-    });
-    let player_pos = {
-        let mut pos = vec2(360. / 2., 240. / 2.);
-        for player_e in &players {
-            pos = *ecs.components.positions.get(player_e).unwrap();
-            break;
-        }
-        pos
-    };
-
-    for sprite_e in &sprites {
-        let position = ecs.components.positions.get(&sprite_e).unwrap();
-        let sprite = ecs.components.animated_sprites.get(&sprite_e).unwrap();
-        let material = ecs.components.materials.get(&sprite_e);
+    let player_pos = ecs
+        .query_ref::<(&PlayerData, &Vec2)>()
+        .next()
+        .map_or(vec2(360. / 2., 240. / 2.), |(_, pos)| *pos);
 
+    for (position, sprite, _, material, flip_to_player, previous_position) in sprites {
         if !sprite.visible {
             continue;
         }
 
-        let flipped =
-            ecs.components.flip_to_player.get(&sprite_e).is_some() && position.x > player_pos.x;
+        let position = previous_position.map_or(*position, |previous| {
+            previous.0.lerp(*position, data.time.alpha)
+        });
+
+        let flipped = flip_to_player.is_some() && position.x > player_pos.x;
 
         if let Some(material) = material {
             //     let mat = data.graphics.materials.get(mat_name).unwrap();
@@ -93,7 +96,7 @@ pub fn draw_animated_sprites(ecs: &Ecs, data: &GameData) {
             gl_use_default_material();
         }
 
-        sprite.draw(data, *position, flipped);
+        sprite.draw(data, position, flipped);
         gl_use_default_material();
     }
 }
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+use crate::{
+    entity::{entities::Ecs, entity_id::Entity, tags::EnemyEntity},
+    physics::spatial_hash::SpatialHash,
+    rand_utils::rand_dir,
+};
+
+use super::collision::Collider;
+
+/// Gap the enemies try to keep between their colliders.
+const SEPARATION_DISTANCE: f32 = 4.;
+/// How much spreading out counts against heading for the target.
+const SEPARATION_WEIGHT: f32 = 1.5;
+
+/// The enemies at the start of the update, so they can keep their distance
+/// from each other instead of stacking up and jittering in the collision
+/// resolution.
+pub struct Crowd {
+    members: Vec<(Entity, Vec2, Collider)>,
+    indices: HashMap<Entity, usize>,
+    spatial_hash: SpatialHash,
+}
+
+impl Crowd {
+    pub fn new(ecs: &Ecs) -> Self {
+        let members = ecs
+            .query_ref::<(Entity, &Vec2, &Collider, &EnemyEntity)>()
+            .map(|(e, pos, coll, _)| (e, *pos, coll.clone()))
+            .collect::<Vec<_>>();
+        let indices = members
+            .iter()
+            .enumerate()
+            .map(|(i, (e, _, _))| (*e, i))
+            .collect();
+        let spatial_hash = SpatialHash::new(&members);
+        Self {
+            members,
+            indices,
+            spatial_hash,
+        }
+    }
+
+    /// Points away from the neighbours that are too close, stronger the
+    /// closer they are.
+    pub fn separation(&self, entity: Entity) -> Vec2 {
+        let Some(index) = self.indices.get(&entity) else {
+            return Vec2::ZERO;
+        };
+        let (_, pos, collider) = &self.members[*index];
+        let radius = collider.shape.bounding_radius();
+
+        let mut separation = Vec2::ZERO;
+        for other_index in self.spatial_hash.query(*pos, radius + SEPARATION_DISTANCE) {
+            if other_index == *index {
+                continue;
+            }
+            let (_, other_pos, other_coll) = &self.members[other_index];
+            let range = radius + other_coll.shape.bounding_radius() + SEPARATION_DISTANCE;
+            let diff = *pos - *other_pos;
+            let distance = diff.length();
+            if distance >= range {
+                continue;
+            }
+            // Enemies spawned on the same spot need a push either way
+            let away = if distance > 0. {
+                diff / distance
+            } else {
+                rand_dir()
+            };
+            separation += away * (1. - distance / range);
+        }
+        separation
+    }
+
+    /// Blends the direction to the target with the separation.
+    pub fn steer(&self, entity: Entity, direction: Vec2) -> Vec2 {
+        (direction + self.separation(entity) * SEPARATION_WEIGHT).normalize_or_zero()
+    }
+}
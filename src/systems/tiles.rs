@@ -0,0 +1,84 @@
+use macroquad::prelude::*;
+
+use crate::{
+    entity::{
+        entities::Ecs,
+        entity_id::Entity,
+        events::DamageEvent,
+        tags::{IceSlide, Velocity},
+    },
+    game_data::GameData,
+    map::map::TileProperty,
+};
+
+use super::collision::{Collider, ColliderType};
+
+const HURT_DAMAGE: f32 = 1.;
+/// Per second standing on an anomaly.
+const ANOMALY_ABERRATION_INCREASE: f32 = 0.05;
+const SLOW_SPEED_MULTIPLIER: f32 = 0.5;
+/// How fast the velocity on ice turns towards the wanted one, per second.
+const ICE_GRIP: f32 = 2.;
+
+/// Applies the effects of the tiles under the player and the enemies. Runs
+/// after they picked their velocity for the step.
+pub fn apply_tile_effects(data: &mut GameData, ecs: &mut Ecs) {
+    let dt = data.time.dt;
+    let map = data.current_map();
+
+    let walkers = ecs
+        .query_ref::<(Entity, &Vec2, &Velocity, &Collider)>()
+        .filter(|(_, _, _, coll)| {
+            matches!(coll.coll_type, ColliderType::Player | ColliderType::Enemy)
+        })
+        .map(|(e, pos, _, _)| (e, *pos))
+        .collect::<Vec<_>>();
+
+    let mut damage_events = vec![];
+    for (entity, pos) in walkers {
+        if map.has_property(pos, TileProperty::Hurt) {
+            // Repeats every step, the invulnerability time limits how often it hits
+            damage_events.push(DamageEvent {
+                source: map.id,
+                target: entity,
+                damage: HURT_DAMAGE,
+                knockback: 0.,
+            });
+        }
+
+        if map.has_property(pos, TileProperty::Anomaly) {
+            if let Some(player_data) = ecs.components.player_data.get_mut(&entity) {
+                player_data.aberration =
+                    (player_data.aberration + ANOMALY_ABERRATION_INCREASE * dt).clamp(0., 1.);
+            }
+        }
+
+        let Some(velocity) = ecs.components.velocities.get_mut(&entity) else {
+            continue;
+        };
+        if map.has_property(pos, TileProperty::Slow) {
+            velocity.0 *= SLOW_SPEED_MULTIPLIER;
+        }
+
+        if map.has_property(pos, TileProperty::Ice) {
+            let slide = match ecs.components.ice_slides.get_mut(&entity) {
+                Some(slide) => {
+                    slide.0 = slide.0.lerp(velocity.0, (ICE_GRIP * dt).min(1.));
+                    slide.0
+                }
+                // Keeps the speed it stepped onto the ice with
+                None => {
+                    ecs.components
+                        .ice_slides
+                        .insert(entity, IceSlide(velocity.0));
+                    velocity.0
+                }
+            };
+            velocity.0 = slide;
+        } else {
+            ecs.components.ice_slides.remove(&entity);
+        }
+    }
+
+    ecs.events.damage.extend(damage_events);
+}
@@ -1,10 +1,7 @@
-use crate::entity::entities::Ecs;
+use crate::{entity::entities::Ecs, timer::Timer};
 
-pub fn update_timers(ecs: &mut Ecs) {
-    let timers = ecs.check_components(|e, comps| comps.timers.contains_key(e));
-
-    for timer_e in &timers {
-        let timer = ecs.components.timers.get_mut(&timer_e).unwrap();
-        timer.update();
+pub fn update_timers(ecs: &mut Ecs, dt: f32) {
+    for timer in ecs.query::<&mut Timer>() {
+        timer.update(dt);
     }
 }
@@ -0,0 +1,77 @@
+use macroquad::audio::{self, PlaySoundParams};
+
+use crate::{
+    entity::{
+        entities::Ecs,
+        events::{CollisionEnded, CollisionStarted, EventReader},
+        spawner::Spawner,
+    },
+    game_data::GameData,
+    map::trigger::TriggerAction,
+};
+
+/// Runs the actions of the trigger volumes the player entered or left since
+/// the last physics step.
+pub fn run_triggers(
+    data: &mut GameData,
+    ecs: &mut Ecs,
+    started_reader: &mut EventReader<CollisionStarted>,
+    ended_reader: &mut EventReader<CollisionEnded>,
+) {
+    let entered = started_reader
+        .read(ecs.events.get::<CollisionStarted>())
+        .map(|e| (e.0, e.1, true));
+    let left = ended_reader
+        .read(ecs.events.get::<CollisionEnded>())
+        .map(|e| (e.0, e.1, false));
+    let transitions = entered.chain(left).collect::<Vec<_>>();
+
+    let mut actions = vec![];
+    for (a, b, entering) in transitions {
+        for (trigger_e, other_e) in [(a, b), (b, a)] {
+            if !ecs.components.player_data.contains(&other_e) {
+                continue;
+            }
+            let Some(trigger) = ecs.components.trigger_volumes.get(&trigger_e) else {
+                continue;
+            };
+            if entering {
+                actions.extend(trigger.on_enter.iter().cloned());
+                if trigger.once {
+                    ecs.despawn(trigger_e);
+                }
+            } else {
+                actions.extend(trigger.on_exit.iter().cloned());
+            }
+        }
+    }
+
+    for action in actions {
+        match action {
+            // A cleared room stays cleared, the wave is skipped
+            TriggerAction::StartWave(wave) if !data.current_room.completed => {
+                let room = &mut data.current_room;
+                room.wave = room.wave.max(wave);
+                for spawner in ecs.query::<&mut Spawner>() {
+                    if spawner.wave == wave {
+                        spawner.active = true;
+                    }
+                }
+            }
+            TriggerAction::StartWave(_) => {}
+            TriggerAction::PlaySound(name) => match data.audio.get(&name) {
+                Some(sound) => audio::play_sound(
+                    sound,
+                    PlaySoundParams {
+                        volume: data.settings.sfx_volume,
+                        ..Default::default()
+                    },
+                ),
+                None => eprintln!("unknown sound `{}`", name),
+            },
+            TriggerAction::LockExits => data.current_room.exits_locked = true,
+            TriggerAction::UnlockExits => data.current_room.exits_locked = false,
+            TriggerAction::ShowDialogue(line) => data.dialogue.show(&line),
+        }
+    }
+}
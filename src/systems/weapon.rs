@@ -9,8 +9,11 @@ use macroquad::{
 use crate::{
     entity::{
         entities::Ecs,
+        entity_id::Entity,
+        hierarchy::attach,
+        player::PlayerData,
         projectile::spawn_bullet,
-        tags::{DamageOnCollision, EntityType},
+        tags::{Ball, DamageOnCollision, EntityType, Velocity},
     },
     game_data::GameData,
     input_manager::Action,
@@ -20,26 +23,26 @@ use crate::{
 use super::collision::ColliderType;
 
 pub fn update_weapon(ecs: &mut Ecs, data: &mut GameData) {
-    let players = ecs.check_components(|e, comps| {
-        comps.player_data.contains_key(e) && comps.positions.contains_key(e)
-    });
+    let dt = data.time.dt;
+    let players = ecs
+        .query_ref::<(Entity, &PlayerData, &Vec2)>()
+        .map(|(e, _, _)| e)
+        .collect::<Vec<_>>();
 
-    let ball_entities = ecs.check_components(|e, comps| {
-        comps.balls.contains_key(e)
-            && comps.positions.contains_key(e)
-            && comps.velocities.contains_key(e)
-    });
+    let ball_entities = ecs
+        .query_ref::<(Entity, &Ball, &Vec2, &Velocity)>()
+        .map(|(e, _, _, _)| e)
+        .collect::<Vec<_>>();
 
     let mut bullet_data = Vec::<(f32, Vec2, Vec2, Option<usize>)>::new();
 
-    let mut player_pos = Vec2::ZERO;
     for player_e in &players {
         let player_position = ecs.components.positions.get_mut(&player_e).unwrap();
         let player_data = ecs.components.player_data.get_mut(&player_e).unwrap();
-        player_pos = *player_position;
+        let player_pos = *player_position;
         match &mut data.weapon {
             Weapon::Launcher(ref mut launcher) => {
-                launcher.shoot_timer.update();
+                launcher.shoot_timer.update(dt);
 
                 let launcher_data = launcher.get_upgraded_data();
 
@@ -75,7 +78,7 @@ pub fn update_weapon(ecs: &mut Ecs, data: &mut GameData) {
                 launcher.shoot_timer.time = launcher_data.timer_duration;
             }
             Weapon::Balls(ref mut balls) => {
-                balls.update();
+                balls.update(dt);
 
                 let balls_data = balls.get_upgraded_data();
 
@@ -84,7 +87,7 @@ pub fn update_weapon(ecs: &mut Ecs, data: &mut GameData) {
                     // let missing_ball_index = ball_entities.iter().find(|e| e.0);
                     let mut ball_ids = (0..balls_data.amount).collect::<Vec<usize>>();
                     for ball_e in &ball_entities {
-                        let id = ecs.components.balls.get(ball_e).unwrap();
+                        let id = &ecs.components.balls.get(ball_e).unwrap().0;
                         if ball_ids.contains(id) {
                             ball_ids = ball_ids
                                 .iter()
@@ -104,23 +107,26 @@ pub fn update_weapon(ecs: &mut Ecs, data: &mut GameData) {
                 }
             }
             Weapon::Dash(ref mut dash) => {
-                dash.update();
+                dash.update(dt);
                 let dash_data = dash.get_upgraded_data();
 
                 if dash.dashing_timer.just_completed() {
                     dash.dashing = false;
                     for shadow_e in &player_data.shadows {
-                        let sprite = ecs.components.animated_sprites.get_mut(&shadow_e).unwrap();
-                        sprite.visible = false;
+                        if let Some(sprite) = ecs.components.animated_sprites.get_mut(shadow_e) {
+                            sprite.visible = false;
+                        }
                     }
                     ecs.components.damage_on_collision.remove(player_e);
                 }
                 if dash.shadow_timer.just_completed() && dash.shadow_index < 2 {
                     let shadow_e = player_data.shadows[dash.shadow_index];
-                    let sprite = ecs.components.animated_sprites.get_mut(&shadow_e).unwrap();
-                    let shadow_pos = ecs.components.positions.get_mut(&shadow_e).unwrap();
-                    sprite.visible = true;
-                    *shadow_pos = player_pos;
+                    if let Some(sprite) = ecs.components.animated_sprites.get_mut(&shadow_e) {
+                        sprite.visible = true;
+                    }
+                    if let Some(shadow_pos) = ecs.components.positions.get_mut(&shadow_e) {
+                        *shadow_pos = player_pos;
+                    }
                     dash.shadow_timer.reset();
                     dash.shadow_index += 1;
                 }
@@ -134,6 +140,7 @@ pub fn update_weapon(ecs: &mut Ecs, data: &mut GameData) {
                             DamageOnCollision {
                                 source: EntityType::Player,
                                 damage: dash_data.damage,
+                                knockback: 150.,
                             },
                         );
                         dash.dashing = true;
@@ -174,18 +181,18 @@ pub fn update_weapon(ecs: &mut Ecs, data: &mut GameData) {
     for ball_e in &ball_entities {
         if let Weapon::Balls(ref mut balls) = data.weapon {
             let balls_data = balls.get_upgraded_data();
-            let position = ecs.components.positions.get_mut(ball_e).unwrap();
-            let ball_index = ecs.components.balls.get_mut(ball_e).unwrap();
+            let ball_index = ecs.components.balls.get(ball_e).unwrap();
 
             let angle = Vec2::from_angle(
-                TAU / balls_data.amount as f32 * *ball_index as f32 + TAU * balls.rotation_progress,
+                TAU / balls_data.amount as f32 * ball_index.0 as f32
+                    + TAU * balls.rotation_progress,
             )
             .rotate(Vec2::X);
 
             let ball_distance = 24.;
-            let desired_pos = player_pos + angle * ball_distance;
-
-            *position = desired_pos;
+            if let Some(local) = ecs.components.local_transforms.get_mut(ball_e) {
+                local.translation = angle * ball_distance;
+            }
         } else {
             ecs.despawn(*ball_e);
         };
@@ -217,7 +224,10 @@ pub fn update_weapon(ecs: &mut Ecs, data: &mut GameData) {
             coll_type,
         );
         if let Some(bullet_index) = bullet_index {
-            ecs.components.balls.insert(bullet_id, *bullet_index);
+            ecs.components.balls.insert(bullet_id, Ball(*bullet_index));
+            if let Some(player_e) = players.first() {
+                attach(ecs, bullet_id, *player_e, Vec2::ZERO);
+            }
         }
     }
 }
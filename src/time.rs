@@ -0,0 +1,152 @@
+use crate::timer::Timer;
+
+/// Length of a gameplay step. The simulation always advances by this much, so
+/// it behaves the same at any frame rate.
+pub const FIXED_DT: f32 = 1. / 120.;
+
+/// Longer frames are clamped, otherwise a hitch (e.g. dragging the window)
+/// would be followed by a burst of steps trying to catch up.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+pub struct Time {
+    /// Time step of the systems that are currently running. `FIXED_DT` in the
+    /// fixed stages, the frame time everywhere else.
+    pub dt: f32,
+    /// How far the simulation is into the next step, from 0 to 1. Rendering
+    /// interpolates between the last two steps by this amount.
+    pub alpha: f32,
+    /// Speed of gameplay, 0 freezes it. Scales how many fixed steps run, not
+    /// their length. The UI keeps running in real time.
+    pub scale: f32,
+    accumulator: f32,
+    hit_stop_timer: Timer,
+    slow_motion_timer: Timer,
+    slow_motion_scale: f32,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self {
+            dt: 0.,
+            alpha: 0.,
+            scale: 1.,
+            accumulator: 0.,
+            hit_stop_timer: Timer::new(0., false),
+            slow_motion_timer: Timer::new(0., false),
+            slow_motion_scale: 1.,
+        }
+    }
+
+    /// Adds the frame time, scaled by the gameplay speed, and returns how many
+    /// fixed steps have to run.
+    pub fn advance(&mut self, frame_time: f32) -> usize {
+        self.update_scale(frame_time);
+
+        self.accumulator += frame_time.min(MAX_FRAME_TIME) * self.scale;
+        let steps = (self.accumulator / FIXED_DT) as usize;
+        self.accumulator -= steps as f32 * FIXED_DT;
+        self.alpha = self.accumulator / FIXED_DT;
+        steps
+    }
+
+    /// Freezes gameplay for a moment to sell a big hit.
+    pub fn hit_stop(&mut self, duration: f32) {
+        self.hit_stop_timer.time = duration;
+        self.hit_stop_timer.reset();
+    }
+
+    /// Drops gameplay to `scale` and eases it back to normal speed over
+    /// `duration` seconds of real time.
+    pub fn slow_motion(&mut self, scale: f32, duration: f32) {
+        self.slow_motion_scale = scale;
+        self.slow_motion_timer.time = duration;
+        self.slow_motion_timer.reset();
+    }
+
+    fn update_scale(&mut self, frame_time: f32) {
+        self.hit_stop_timer.update(frame_time);
+        self.slow_motion_timer.update(frame_time);
+
+        self.scale = if !self.hit_stop_timer.completed() {
+            0.
+        } else if !self.slow_motion_timer.completed() {
+            let progress = 1. - self.slow_motion_timer.progress();
+            self.slow_motion_scale + (1. - self.slow_motion_scale) * progress * progress
+        } else {
+            1.
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_a_step_per_fixed_dt_and_keeps_the_rest() {
+        let mut time = Time::new();
+
+        assert_eq!(time.advance(FIXED_DT * 3.5), 3);
+        assert!((time.alpha - 0.5).abs() < 1e-3);
+        // The leftover half step completes with the next frame
+        assert_eq!(time.advance(FIXED_DT * 0.75), 1);
+        assert!((time.alpha - 0.25).abs() < 1e-3);
+    }
+
+    #[test]
+    fn short_frames_run_no_step() {
+        let mut time = Time::new();
+
+        assert_eq!(time.advance(FIXED_DT * 0.4), 0);
+        assert!((time.alpha - 0.4).abs() < 1e-3);
+    }
+
+    #[test]
+    fn clamps_long_frames() {
+        let mut time = Time::new();
+        let mut clamped = Time::new();
+
+        assert_eq!(time.advance(10.), clamped.advance(MAX_FRAME_TIME));
+        assert_eq!(time.alpha, clamped.alpha);
+    }
+
+    #[test]
+    fn hit_stop_freezes_gameplay_for_its_duration() {
+        let mut time = Time::new();
+        time.hit_stop(0.1);
+
+        assert_eq!(time.advance(0.05), 0);
+        assert_eq!(time.scale, 0.);
+        assert_eq!(time.advance(0.06), 7);
+        assert_eq!(time.scale, 1.);
+    }
+
+    #[test]
+    fn slow_motion_eases_back_to_normal_speed() {
+        let mut time = Time::new();
+        time.slow_motion(0.2, 1.);
+
+        time.advance(0.01);
+        let early = time.scale;
+        time.advance(0.5);
+        let later = time.scale;
+        assert!(early >= 0.2 && early < later && later < 1.);
+        time.advance(0.5);
+        assert_eq!(time.scale, 1.);
+    }
+
+    #[test]
+    fn slow_motion_runs_steps_at_its_speed() {
+        let mut time = Time::new();
+        time.slow_motion(0.5, 10.);
+
+        let steps = (0..10).map(|_| time.advance(FIXED_DT * 10.)).sum::<usize>();
+        assert!((45..=55).contains(&steps));
+    }
+}
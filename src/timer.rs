@@ -1,5 +1,3 @@
-use macroquad::time::get_frame_time;
-
 #[derive(Clone)]
 pub struct Timer {
     pub time: f32,
@@ -28,7 +26,7 @@ impl Timer {
         self.previously_completed = false;
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, dt: f32) {
         if self.paused {
             return;
         }
@@ -39,7 +37,7 @@ impl Timer {
         if !self.repeating {
             self.completed = false;
         }
-        self.remaining_time -= get_frame_time();
+        self.remaining_time -= dt;
         if self.remaining_time <= 0. {
             self.completed = true;
         }
@@ -67,3 +65,58 @@ impl Timer {
         self.completed = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_once_the_time_ran_out() {
+        let mut timer = Timer::new(1., false);
+
+        timer.update(0.6);
+        assert!(!timer.completed());
+        assert!((timer.progress() - 0.4).abs() < 1e-6);
+        timer.update(0.6);
+        assert!(timer.completed());
+        assert!(timer.just_completed());
+        assert_eq!(timer.progress(), 0.);
+        timer.update(0.6);
+        assert!(timer.completed());
+        assert!(!timer.just_completed());
+    }
+
+    #[test]
+    fn repeating_timers_restart_after_completing() {
+        let mut timer = Timer::new(1., true);
+
+        timer.update(1.);
+        assert!(timer.just_completed());
+        timer.update(0.5);
+        assert!(!timer.completed());
+        timer.update(0.5);
+        assert!(timer.just_completed());
+    }
+
+    #[test]
+    fn paused_timers_keep_their_time() {
+        let mut timer = Timer::new(1., false);
+
+        timer.set_paused(true);
+        timer.update(2.);
+        assert!(!timer.completed());
+        timer.set_paused(false);
+        timer.update(1.);
+        assert!(timer.completed());
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut timer = Timer::new(1., false);
+
+        timer.update(1.);
+        timer.reset();
+        assert!(!timer.completed());
+        assert_eq!(timer.progress(), 1.);
+    }
+}
@@ -0,0 +1,52 @@
+use macroquad::prelude::*;
+
+use crate::{game_data::GameData, timer::Timer};
+
+/// A line of text shown at the bottom of the screen for a few seconds.
+pub struct Dialogue {
+    line: Option<String>,
+    timer: Timer,
+}
+
+impl Dialogue {
+    pub fn new() -> Self {
+        Self {
+            line: None,
+            timer: Timer::new(4., false),
+        }
+    }
+
+    pub fn show(&mut self, line: &str) {
+        self.line = Some(line.to_string());
+        self.timer.reset();
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.timer.update(dt);
+        if self.timer.just_completed() {
+            self.line = None;
+        }
+    }
+
+    pub fn draw(&self, data: &GameData) {
+        let Some(line) = &self.line else {
+            return;
+        };
+
+        let text_size = 16;
+        let text_center = get_text_center(line, Some(&data.ui.font), text_size, 1., 0.);
+        for (offset, color) in [(1., data.ui.text_shadow_color), (0., data.ui.text_color)] {
+            draw_text_ex(
+                line,
+                360. / 2. - text_center.x,
+                220. + offset,
+                TextParams {
+                    font_size: text_size,
+                    font: Some(&data.ui.font),
+                    color,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
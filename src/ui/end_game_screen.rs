@@ -29,11 +29,11 @@ impl EndGameScreen {
         }
     }
 
-    pub fn update(&mut self) {
-        self.start_timer.update();
-        self.timer.update();
-        self.text_timer.update();
-        self.show_button_timer.update();
+    pub fn update(&mut self, dt: f32) {
+        self.start_timer.update(dt);
+        self.timer.update(dt);
+        self.text_timer.update(dt);
+        self.show_button_timer.update(dt);
 
         if self.start_timer.just_completed() {
             self.timer.reset();
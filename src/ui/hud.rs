@@ -6,7 +6,11 @@ use macroquad::{
     },
 };
 
-use crate::{entity::entities::Ecs, game_data::GameData, sprite::indexed_sprite::IndexedSprite};
+use crate::{
+    entity::{entities::Ecs, player::PlayerData, tags::Health},
+    game_data::GameData,
+    sprite::indexed_sprite::IndexedSprite,
+};
 
 pub struct HudHearts {
     sprite: IndexedSprite,
@@ -20,14 +24,8 @@ impl HudHearts {
     }
 
     pub fn draw(&self, data: &GameData, ecs: &Ecs) {
-        let players = ecs.check_components(|e, comps| {
-            comps.player_data.contains_key(e) && comps.health.contains_key(e)
-        });
-
         let start_pos = vec2(16., 0.);
-        for player_e in players {
-            let player = ecs.components.player_data.get(&player_e).unwrap();
-            let health = ecs.components.health.get(&player_e).unwrap();
+        for (player, health) in ecs.query_ref::<(&PlayerData, &Health)>() {
             let up_data = player.get_upgraded_data();
 
             for i in 0..up_data.max_hp {
@@ -55,12 +53,8 @@ impl AberrationMeter {
     }
 
     pub fn draw(&self, data: &GameData, ecs: &Ecs) {
-        let players = ecs.check_components(|e, comps| comps.player_data.contains_key(e));
-
         let pos = vec2(308., 78.);
-        for player_e in players {
-            let player = ecs.components.player_data.get(&player_e).unwrap();
-
+        for player in ecs.query_ref::<&PlayerData>() {
             gl_use_material(&data.graphics.aberration_meter_material);
 
             let h = player.aberration * 65.;
@@ -0,0 +1,210 @@
+use macroquad::prelude::*;
+
+use crate::{
+    entity::{
+        entities::Ecs,
+        entity_id::Entity,
+        inspect::{Field, FieldValue},
+    },
+    game_data::GameData,
+};
+
+const PANEL_X: f32 = 196.;
+const LINE_HEIGHT: f32 = 9.;
+const MAX_LINES: usize = 25;
+
+/// Debug overlay that shows the components of an entity and lets numbers be
+/// edited live. Toggled with F4, only in debug builds.
+///
+/// Tab / Shift+Tab or a click selects an entity, PageUp / PageDown picks a
+/// field and -/+ changes it (Shift x10, Ctrl x0.1).
+#[derive(Default)]
+pub struct Inspector {
+    visible: bool,
+    selected: Option<Entity>,
+    field_index: usize,
+}
+
+impl Inspector {
+    pub fn update_and_draw(&mut self, data: &GameData, ecs: &mut Ecs) {
+        if is_key_pressed(KeyCode::F4) {
+            self.visible = !self.visible;
+        }
+        if !self.visible {
+            return;
+        }
+
+        if let Some(selected) = self.selected {
+            if !ecs.is_alive(selected) {
+                self.selected = None;
+            }
+        }
+        self.select_entity(data, ecs);
+
+        draw_rectangle(
+            PANEL_X,
+            0.,
+            360. - PANEL_X,
+            240.,
+            Color::from_rgba(0, 0, 0, 200),
+        );
+
+        let Some(entity) = self.selected else {
+            draw_line_text(data, "Tab or click to inspect", 0, WHITE);
+            return;
+        };
+
+        if let Some(pos) = ecs.components.positions.get(&entity) {
+            draw_circle_lines(pos.x, pos.y, 8., 1., YELLOW);
+        }
+
+        let mut components = ecs.components.inspect(&entity);
+        let editable_count = components
+            .iter()
+            .flat_map(|(_, fields)| fields)
+            .filter(|field| is_editable(field))
+            .count();
+        if editable_count > 0 {
+            if is_key_pressed(KeyCode::PageDown) {
+                self.field_index = (self.field_index + 1) % editable_count;
+            }
+            if is_key_pressed(KeyCode::PageUp) {
+                self.field_index = (self.field_index + editable_count - 1) % editable_count;
+            }
+            self.field_index = self.field_index.min(editable_count - 1);
+        }
+
+        let mut lines = vec![(
+            format!("entity {} gen {}", entity.index, entity.generation),
+            WHITE,
+        )];
+        let mut selected_line = 0;
+        let mut editable_index = 0;
+        for (name, fields) in &mut components {
+            lines.push((name.to_string(), data.ui.text_color));
+            for field in fields {
+                let mut color = LIGHTGRAY;
+                if is_editable(field) {
+                    if editable_index == self.field_index {
+                        edit_field(&mut field.value);
+                        selected_line = lines.len();
+                        color = YELLOW;
+                    }
+                    editable_index += 1;
+                }
+                let value = match &field.value {
+                    FieldValue::Float(value) => format!("{:.3}", value),
+                    FieldValue::Bool(value) => value.to_string(),
+                    FieldValue::Text(value) => value.clone(),
+                };
+                lines.push((format!("  {}: {}", field.name, value), color));
+            }
+        }
+
+        let first_line = selected_line.saturating_sub(MAX_LINES - 1);
+        for (i, (text, color)) in lines.iter().skip(first_line).take(MAX_LINES).enumerate() {
+            draw_line_text(data, text, i, *color);
+        }
+    }
+
+    fn select_entity(&mut self, data: &GameData, ecs: &Ecs) {
+        let candidates = ecs
+            .entities
+            .entities()
+            .iter()
+            .filter(|e| ecs.components.positions.contains(e))
+            .copied()
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return;
+        }
+
+        if is_key_pressed(KeyCode::Tab) {
+            let current = self
+                .selected
+                .and_then(|selected| candidates.iter().position(|e| *e == selected));
+            let index = match current {
+                None => 0,
+                Some(i) if is_key_down(KeyCode::LeftShift) => {
+                    (i + candidates.len() - 1) % candidates.len()
+                }
+                Some(i) => (i + 1) % candidates.len(),
+            };
+            self.selected = Some(candidates[index]);
+            self.field_index = 0;
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let mouse_pos = mouse_world_position(&data.camera);
+            let closest = candidates
+                .iter()
+                .filter_map(|e| {
+                    let pos = ecs.components.positions.get(e)?;
+                    Some((*e, pos.distance(mouse_pos)))
+                })
+                .filter(|(_, distance)| *distance < 10.)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+            if let Some((entity, _)) = closest {
+                self.selected = Some(entity);
+                self.field_index = 0;
+            }
+        }
+    }
+}
+
+/// The cursor in the y-down world the entities are drawn in. The game camera
+/// renders to a target, which makes its own `screen_to_world` flip y, so the
+/// cursor goes through the same camera as if it drew to the screen.
+fn mouse_world_position(camera: &Camera2D) -> Vec2 {
+    let screen_camera = Camera2D {
+        rotation: camera.rotation,
+        zoom: camera.zoom,
+        target: camera.target,
+        offset: camera.offset,
+        render_target: None,
+        viewport: camera.viewport,
+    };
+    screen_camera.screen_to_world(mouse_position().into())
+}
+
+fn is_editable(field: &Field) -> bool {
+    !matches!(field.value, FieldValue::Text(_))
+}
+
+fn edit_field(value: &mut FieldValue) {
+    let decrease = is_key_pressed(KeyCode::Minus);
+    let increase = is_key_pressed(KeyCode::Equal);
+    if !decrease && !increase {
+        return;
+    }
+
+    match value {
+        FieldValue::Float(value) => {
+            let step = if is_key_down(KeyCode::LeftShift) {
+                10.
+            } else if is_key_down(KeyCode::LeftControl) {
+                0.1
+            } else {
+                1.
+            };
+            **value += if increase { step } else { -step };
+        }
+        FieldValue::Bool(value) => **value = !**value,
+        FieldValue::Text(_) => {}
+    }
+}
+
+fn draw_line_text(data: &GameData, text: &str, line: usize, color: Color) {
+    draw_text_ex(
+        text,
+        PANEL_X + 2.,
+        LINE_HEIGHT * (line + 1) as f32,
+        TextParams {
+            font: Some(&data.ui.font),
+            font_size: 16,
+            font_scale: 0.5,
+            color,
+            ..Default::default()
+        },
+    );
+}
@@ -9,11 +9,17 @@ use macroquad::{
 use crate::{
     entity::animated_sprite::{AnimatedSprite, Animation},
     game_data::GameData,
+    input_manager::Action,
     sprite::indexed_sprite::IndexedSprite,
 };
 
 use super::button::button;
 
+pub enum IntroChoice {
+    Start,
+    Continue,
+}
+
 pub struct IntroScreen {
     pub sprite: AnimatedSprite,
 }
@@ -31,29 +37,64 @@ impl IntroScreen {
         }
     }
 
-    pub fn update_and_draw(&mut self, data: &mut GameData) -> bool {
-        let mut should_start = false;
+    pub fn update_and_draw(&mut self, data: &mut GameData) -> Option<IntroChoice> {
+        let mut choice = None;
 
+        let continue_id = hash!();
         let start_id = hash!();
 
-        let ids = vec![start_id];
+        let ids = if data.has_save {
+            vec![continue_id, start_id]
+        } else {
+            vec![start_id]
+        };
 
-        if data.ui.focus.is_none() {
+        if data.ui.focus.is_none() || !ids.contains(&data.ui.focus.unwrap()) {
             data.ui.focus = Some(ids[0]);
         }
+        let focus = data.ui.focus.unwrap();
+
+        let current_index = ids.iter().position(|s| s == &focus).unwrap();
+        if ids.len() > 1
+            && (data.input.is_just_pressed(Action::Up) || data.input.is_just_pressed(Action::Down))
+        {
+            data.ui.focus = Some(ids[(current_index + 1) % ids.len()]);
+            audio::play_sound(
+                &data.audio.ui_switch,
+                PlaySoundParams {
+                    volume: data.settings.sfx_volume,
+                    ..Default::default()
+                },
+            );
+        }
 
         let center = vec2(360. / 2., 240. / 2.);
 
         let button_width = 90.;
+        if data.has_save
+            && button(
+                data,
+                &Rect::new(center.x - button_width / 2., 136., button_width, 20.),
+                focus == continue_id,
+                "Continue",
+                None,
+                Vec2::ZERO,
+            )
+        {
+            choice = Some(IntroChoice::Continue);
+        }
         if button(
             data,
             &Rect::new(center.x - button_width / 2., 160., button_width, 20.),
-            true,
+            focus == start_id,
             "Start Game",
             None,
             Vec2::ZERO,
         ) {
-            should_start = true;
+            choice = Some(IntroChoice::Start);
+        }
+
+        if choice.is_some() {
             audio::play_sound(
                 &data.audio.confirm2,
                 PlaySoundParams {
@@ -63,9 +104,9 @@ impl IntroScreen {
             );
         }
 
-        self.sprite.update();
+        self.sprite.update(data.time.dt);
         self.sprite.draw(data, Vec2::ZERO, false);
 
-        should_start
+        choice
     }
 }
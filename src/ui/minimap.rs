@@ -0,0 +1,158 @@
+use macroquad::prelude::*;
+
+use crate::{
+    floor::{Floor, RoomKind},
+    game_data::GameData,
+    input_manager::Action,
+};
+
+use super::nine_slice::nice_slice;
+
+/// Rooms this many cells away from the current one still fit on the minimap.
+const MINIMAP_RANGE: i32 = 2;
+
+/// The rooms around the current one in the corner of the screen.
+pub struct Minimap {
+    center: Vec2,
+    room_size: f32,
+    spacing: f32,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Self {
+            center: vec2(330., 36.),
+            room_size: 8.,
+            spacing: 10.,
+        }
+    }
+
+    pub fn draw(&self, data: &GameData) {
+        let floor = &data.floor;
+        let current = floor.current().cell;
+        let size = (MINIMAP_RANGE * 2 + 1) as f32 * self.spacing + 2.;
+        draw_rectangle(
+            self.center.x - size / 2.,
+            self.center.y - size / 2.,
+            size,
+            size,
+            Color::from_rgba(0, 0, 0, 120),
+        );
+
+        let rooms = known_rooms(floor)
+            .into_iter()
+            .filter(|room| {
+                let (x, y) = floor.rooms[*room].cell;
+                (x - current.0).abs() <= MINIMAP_RANGE && (y - current.1).abs() <= MINIMAP_RANGE
+            })
+            .collect::<Vec<_>>();
+        let origin = self.center - vec2(current.0 as f32, current.1 as f32) * self.spacing;
+        draw_rooms(floor, &rooms, origin, self.room_size, self.spacing);
+    }
+}
+
+/// The whole floor as far as it's known, opened with the map button from the
+/// pause menu.
+pub fn floor_map(data: &mut GameData) {
+    if data.input.is_just_pressed(Action::Map) {
+        data.show_floor_map = !data.show_floor_map;
+    }
+    if !data.show_floor_map {
+        return;
+    }
+
+    let frame = Rect::new(20., 10., 320., 220.);
+    nice_slice(
+        &data.ui.frame_texture,
+        &RectOffset::new(3., 3., 3., 3.),
+        &frame,
+    );
+
+    let text_size = 16;
+    let title = "Floor";
+    let text_center = get_text_center(title, Some(&data.ui.font), text_size, 1., 0.);
+    for (offset, color) in [(1., data.ui.text_shadow_color), (0., data.ui.text_color)] {
+        draw_text_ex(
+            title,
+            frame.center().x - text_center.x,
+            frame.y + 4. + text_size as f32 - 2. + offset,
+            TextParams {
+                font_size: text_size,
+                font: Some(&data.ui.font),
+                color,
+                ..Default::default()
+            },
+        );
+    }
+
+    let floor = &data.floor;
+    let rooms = known_rooms(floor);
+    let (min, max) = rooms.iter().fold(
+        (ivec2(i32::MAX, i32::MAX), ivec2(i32::MIN, i32::MIN)),
+        |(min, max), room| {
+            let cell = IVec2::from(floor.rooms[*room].cell);
+            (min.min(cell), max.max(cell))
+        },
+    );
+    let spacing = 24.;
+    let cells_center = (min + max).as_vec2() / 2.;
+    let origin = frame.center() + vec2(0., 6.) - cells_center * spacing;
+    draw_rooms(floor, &rooms, origin, 18., spacing);
+}
+
+/// Every visited room and the rooms behind their doors.
+fn known_rooms(floor: &Floor) -> Vec<usize> {
+    (0..floor.rooms.len())
+        .filter(|room| {
+            floor.rooms[*room].visited
+                || floor.rooms[*room]
+                    .doors
+                    .values()
+                    .any(|next| floor.rooms[*next].visited)
+        })
+        .collect()
+}
+
+/// Draws the rooms with the doors between them, room cells are `spacing`
+/// apart starting at `origin`.
+fn draw_rooms(floor: &Floor, rooms: &[usize], origin: Vec2, room_size: f32, spacing: f32) {
+    let position = |room: usize| {
+        let (x, y) = floor.rooms[room].cell;
+        origin + vec2(x as f32, y as f32) * spacing
+    };
+    let door_color = Color::from_rgba(140, 140, 150, 255);
+
+    for room in rooms {
+        for next in floor.rooms[*room].doors.values() {
+            if rooms.contains(next) {
+                let (from, to) = (position(*room), position(*next));
+                draw_line(from.x, from.y, to.x, to.y, 1., door_color);
+            }
+        }
+    }
+
+    for room in rooms {
+        let floor_room = &floor.rooms[*room];
+        let pos = position(*room) - Vec2::splat(room_size / 2.);
+        if !floor_room.visited {
+            draw_rectangle(pos.x, pos.y, room_size, room_size, BLACK);
+            draw_rectangle_lines(pos.x, pos.y, room_size, room_size, 1., door_color);
+        } else {
+            let color = if *room == floor.current {
+                Color::from_rgba(232, 193, 112, 255)
+            } else if floor_room.cleared {
+                Color::from_rgba(90, 90, 100, 255)
+            } else {
+                Color::from_rgba(122, 36, 61, 255)
+            };
+            draw_rectangle(pos.x, pos.y, room_size, room_size, color);
+        }
+
+        let center = position(*room);
+        match floor_room.kind {
+            RoomKind::Boss => draw_poly(center.x, center.y, 4, room_size * 0.3, 45., RED),
+            RoomKind::Treasure => draw_circle(center.x, center.y, room_size * 0.2, YELLOW),
+            RoomKind::Start | RoomKind::Combat => {}
+        }
+    }
+}
@@ -4,7 +4,10 @@ use macroquad::{
     texture::{draw_texture, Texture2D},
 };
 
-use crate::{entity::entities::Ecs, game_data::GameData};
+use crate::{
+    entity::{entities::Ecs, mirituhg::Mirituhg, tags::Health},
+    game_data::GameData,
+};
 
 use super::nine_slice;
 
@@ -22,14 +25,7 @@ impl HudMirituhg {
     }
 
     pub fn draw(&self, _data: &GameData, ecs: &Ecs) {
-        let mirituhgs = ecs.check_components(|e, comps| {
-            comps.mirituhg.contains_key(e) && comps.health.contains_key(e)
-        });
-
-        for mirituhg_e in mirituhgs {
-            let mirituhg = ecs.components.mirituhg.get(&mirituhg_e).unwrap();
-            let health = ecs.components.health.get(&mirituhg_e).unwrap();
-
+        for (mirituhg, health) in ecs.query_ref::<(&Mirituhg, &Health)>() {
             draw_texture(&self.hud_texture, 0., 0., WHITE);
 
             nine_slice::nice_slice(
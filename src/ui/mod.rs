@@ -1,10 +1,14 @@
 pub mod button;
 pub mod death_screen;
+pub mod dialogue;
 pub mod end_game_screen;
 pub mod hud;
 pub mod icon;
 pub mod in_rect;
+#[cfg(debug_assertions)]
+pub mod inspector;
 pub mod intro_screen;
+pub mod minimap;
 pub mod mirituhg;
 pub mod nine_slice;
 pub mod pause_menu;
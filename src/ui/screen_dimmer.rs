@@ -20,9 +20,9 @@ impl ScreenDimmer {
         self.timer.reset();
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, dt: f32) {
         self.just_dimmed = false;
-        self.timer.update();
+        self.timer.update(dt);
         if self.dimming {
             if self.timer.just_completed() {
                 self.just_dimmed = true;
@@ -1,6 +1,17 @@
 use macroquad::{prelude::*, ui::hash};
 
-use crate::{entity::upgrades::Upgrade, game_data::GameData, input_manager::Action};
+use crate::{
+    entity::{
+        entities::Ecs,
+        events::{EventReader, UpgradeChosen},
+        player::PlayerData,
+        tags::Health,
+        upgrades::{CommonUpgrade, ItemUpgrade, Upgrade, WeaponUpgrade},
+    },
+    game_data::GameData,
+    input_manager::Action,
+    items::weapon::{Balls, Dash, Launcher, Weapon, WeaponType},
+};
 
 use super::{button::button, nine_slice::nice_slice};
 
@@ -19,6 +30,33 @@ impl UpgradeScreen {
         }
     }
 
+    pub fn update(&mut self, data: &mut GameData, ecs: &mut Ecs) {
+        if is_key_pressed(KeyCode::F3) {
+            self.visible = true;
+            data.paused = true;
+        }
+
+        if data.pause_timer.just_completed() && !data.show_pause_menu && !self.visible {
+            data.paused = false;
+        }
+
+        if data.current_room.started && !data.current_room.upgrade_chosen {
+            self.visible = true;
+        }
+
+        if data.paused && !data.show_pause_menu && self.visible {
+            self.upgrades = data.current_room.available_upgrades.clone();
+            if let Some(upgrade) = self.draw(data) {
+                ecs.events.send(UpgradeChosen(upgrade));
+
+                data.current_room.upgrade_chosen = true;
+
+                self.visible = false;
+                data.paused = false;
+            }
+        }
+    }
+
     pub fn draw(&self, data: &mut GameData) -> Option<Upgrade> {
         let mut chosen_upgrade: Option<Upgrade> = None;
 
@@ -227,3 +265,73 @@ impl UpgradeScreen {
         chosen_upgrade
     }
 }
+
+pub fn apply_upgrades(data: &mut GameData, ecs: &mut Ecs, reader: &mut EventReader<UpgradeChosen>) {
+    let chosen = reader
+        .read(ecs.events.get::<UpgradeChosen>())
+        .map(|e| e.0.clone())
+        .collect::<Vec<_>>();
+
+    for upgrade in &chosen {
+        apply_upgrade(data, ecs, upgrade);
+    }
+}
+
+fn apply_upgrade(data: &mut GameData, ecs: &mut Ecs, upgrade: &Upgrade) {
+    let (player_data, health) = ecs
+        .query::<(&mut PlayerData, &mut Health)>()
+        .next()
+        .unwrap();
+
+    match upgrade {
+        Upgrade::Item(ref item) => match item {
+            ItemUpgrade::Hp(hp) => {
+                health.hp += *hp;
+            }
+            ItemUpgrade::AnomalySmall => {
+                player_data.aberration = (player_data.aberration - 0.1).max(0.);
+            }
+            ItemUpgrade::AnomalyBig => {
+                player_data.aberration = (player_data.aberration - 0.5).max(0.);
+            }
+        },
+        Upgrade::CommonUpgrade(ref upgrade) => match upgrade {
+            CommonUpgrade::MaxHp(hp) => {
+                health.hp += *hp as f32;
+                player_data.upgrades.push(upgrade.clone())
+            }
+            CommonUpgrade::MoveSpeed(_) => player_data.upgrades.push(upgrade.clone()),
+            CommonUpgrade::ItemDropChance(increase) => {
+                data.item_drop_chance_increase += increase;
+            }
+        },
+        Upgrade::Weapon(ref weapon) => match weapon {
+            WeaponType::Launcher => {
+                data.weapon = Weapon::Launcher(Launcher::new());
+            }
+            WeaponType::Balls => {
+                data.weapon = Weapon::Balls(Balls::new());
+            }
+            WeaponType::Dash => {
+                data.weapon = Weapon::Dash(Dash::new());
+            }
+        },
+        Upgrade::WeaponUpgrade(ref upgrade) => match upgrade {
+            WeaponUpgrade::Launcher(ref upgrade) => {
+                if let Weapon::Launcher(ref mut launcher) = data.weapon {
+                    launcher.upgrades.push(upgrade.clone());
+                }
+            }
+            WeaponUpgrade::Balls(ref upgrade) => {
+                if let Weapon::Balls(ref mut balls) = data.weapon {
+                    balls.upgrades.push(upgrade.clone());
+                }
+            }
+            WeaponUpgrade::Dash(ref upgrade) => {
+                if let Weapon::Dash(ref mut dash) = data.weapon {
+                    dash.upgrades.push(upgrade.clone());
+                }
+            }
+        },
+    }
+}